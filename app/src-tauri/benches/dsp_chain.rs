@@ -0,0 +1,83 @@
+//! Cost of the capture DSP chain on one second of 16 kHz audio. Anything well
+//! below one second per iteration keeps up with the microphone on one core.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gorlami_lib::dsp::{DspChain, DspConfig};
+use std::hint::black_box;
+
+const SAMPLE_RATE: u32 = 16000;
+
+/// A voiced-speech-like tone with background hiss and a DC offset.
+fn noisy_speech(seconds: u32) -> Vec<i16> {
+    let mut seed = 0x2545_f491_u32;
+    (0..SAMPLE_RATE * seconds)
+        .map(|i| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let noise = (seed as f32 / u32::MAX as f32 - 0.5) * 0.05;
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let voice = 0.2
+                * (std::f32::consts::TAU * 180.0 * t).sin()
+                * (std::f32::consts::TAU * 3.0 * t).sin().abs();
+            ((voice + noise + 0.02) * i16::MAX as f32) as i16
+        })
+        .collect()
+}
+
+fn bench_dsp_chain(c: &mut Criterion) {
+    let input = noisy_speech(1);
+    let all_stages = DspConfig {
+        high_pass_enabled: true,
+        noise_suppression_enabled: true,
+        agc_enabled: true,
+        ..DspConfig::default()
+    };
+    let configs = [
+        (
+            "high_pass",
+            DspConfig {
+                high_pass_enabled: true,
+                ..DspConfig::default()
+            },
+        ),
+        (
+            "noise_suppression",
+            DspConfig {
+                noise_suppression_enabled: true,
+                ..DspConfig::default()
+            },
+        ),
+        (
+            "agc",
+            DspConfig {
+                agc_enabled: true,
+                ..DspConfig::default()
+            },
+        ),
+        ("full_chain", all_stages),
+    ];
+
+    let mut group = c.benchmark_group("dsp_chain_1s_16khz");
+    group.throughput(Throughput::Elements(input.len() as u64));
+
+    for (name, config) in configs {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &config, |b, config| {
+            let mut chain = DspChain::new(config, SAMPLE_RATE);
+            let mut output = Vec::with_capacity(input.len());
+            b.iter(|| {
+                output.clear();
+                // Feed 10ms blocks, like the capture callback does
+                for block in input.chunks(SAMPLE_RATE as usize / 100) {
+                    chain.process(black_box(block), &mut output);
+                }
+                black_box(&output);
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_dsp_chain);
+criterion_main!(benches);
@@ -0,0 +1,771 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Receives interleaved samples in the range [-1.0, 1.0], with the time the
+/// first of them was captured. Capture times follow the device clock, mapped
+/// onto `Instant` once per stream, so they stay evenly spaced however late
+/// the callbacks run.
+pub type SampleCallback = Box<dyn FnMut(&[f32], Instant) + Send + 'static>;
+
+/// Receives errors reported while a source is running.
+pub type ErrorCallback = Box<dyn FnMut(String) + Send + 'static>;
+
+/// What a prepared source is about to deliver.
+#[derive(Debug, Clone)]
+pub struct SourceFormat {
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// The requested device, if it was unavailable and another one is used instead.
+    pub fallback_from: Option<String>,
+}
+
+/// Somewhere audio comes from: an input device, a file or a generator. The
+/// recorder prepares a source to learn its format, then starts it with a
+/// callback built for that format.
+pub trait AudioSource: Send {
+    /// Picks the device to capture from, `None` meaning the default one.
+    fn prepare(&mut self, device: Option<&str>) -> Result<SourceFormat, String>;
+
+    /// Starts delivering audio from the prepared device. Delivery stops when the
+    /// returned stream is paused or dropped.
+    fn start(
+        &mut self,
+        on_data: SampleCallback,
+        on_error: ErrorCallback,
+    ) -> Result<Box<dyn SourceStream>, String>;
+
+    /// Whether the named device can still be captured from. Sources without
+    /// removable devices are always available.
+    fn is_available(&self, _device_name: &str) -> bool {
+        true
+    }
+}
+
+/// A running source.
+pub trait SourceStream {
+    fn pause(&self) -> Result<(), String>;
+}
+
+/// Captures from a cpal input device.
+#[derive(Default)]
+pub struct CpalSource {
+    device: Option<cpal::Device>,
+}
+
+impl CpalSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AudioSource for CpalSource {
+    fn prepare(&mut self, device: Option<&str>) -> Result<SourceFormat, String> {
+        let host = cpal::default_host();
+
+        // Open the requested input device, falling back to the default
+        let mut fallback_from = None;
+        let mut found = None;
+        if let Some(name) = device {
+            found = host
+                .input_devices()
+                .map_err(|e| format!("Failed to enumerate input devices: {}", e))?
+                .find(|d| d.name().map(|n| n == name).unwrap_or(false));
+            if found.is_none() {
+                fallback_from = Some(name.to_string());
+            }
+        }
+        let device = match found {
+            Some(device) => device,
+            None => host
+                .default_input_device()
+                .ok_or_else(|| "No default input device found".to_string())?,
+        };
+
+        let config = device
+            .default_input_config()
+            .map_err(|e| format!("Failed to get default input config: {}", e))?;
+
+        let format = SourceFormat {
+            device_name: device
+                .name()
+                .unwrap_or_else(|_| "Unknown device".to_string()),
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            fallback_from,
+        };
+        self.device = Some(device);
+        Ok(format)
+    }
+
+    fn start(
+        &mut self,
+        on_data: SampleCallback,
+        mut on_error: ErrorCallback,
+    ) -> Result<Box<dyn SourceStream>, String> {
+        let device = self
+            .device
+            .take()
+            .ok_or_else(|| "Audio source was not prepared".to_string())?;
+
+        let config = device
+            .default_input_config()
+            .map_err(|e| format!("Failed to get default input config: {}", e))?;
+        let sample_format = config.sample_format();
+        let stream_config: cpal::StreamConfig = config.into();
+
+        let error_callback = move |err: cpal::StreamError| on_error(err.to_string());
+
+        // Build the stream
+        let stream = match sample_format {
+            cpal::SampleFormat::I8 => {
+                build_input_stream::<i8>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::I16 => {
+                build_input_stream::<i16>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::I24 => {
+                build_input_stream::<cpal::I24>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::I32 => {
+                build_input_stream::<i32>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::I64 => {
+                build_input_stream::<i64>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::U8 => {
+                build_input_stream::<u8>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::U16 => {
+                build_input_stream::<u16>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::U32 => {
+                build_input_stream::<u32>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::U64 => {
+                build_input_stream::<u64>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::F32 => {
+                build_input_stream::<f32>(&device, &stream_config, on_data, error_callback)
+            }
+            cpal::SampleFormat::F64 => {
+                build_input_stream::<f64>(&device, &stream_config, on_data, error_callback)
+            }
+            other => {
+                return Err(format!("Unsupported sample format: {}", other));
+            }
+        }
+        .map_err(|e| format!("Failed to build input stream: {}", e))?;
+
+        // Start the stream
+        stream
+            .play()
+            .map_err(|e| format!("Failed to start stream: {}", e))?;
+
+        Ok(Box::new(stream))
+    }
+
+    fn is_available(&self, device_name: &str) -> bool {
+        cpal::default_host()
+            .input_devices()
+            .map(|mut devices| devices.any(|d| d.name().map(|n| n == device_name).unwrap_or(false)))
+            .unwrap_or(true)
+    }
+}
+
+/// One input callback for every sample type: converts to f32 and hands the
+/// block to `on_data` with the device's capture timestamp.
+fn build_input_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut on_data: SampleCallback,
+    error_callback: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let mut scratch: Vec<f32> = Vec::new();
+    let mut first_capture: Option<(cpal::StreamInstant, Instant)> = None;
+
+    device.build_input_stream(
+        config,
+        move |data: &[T], info: &cpal::InputCallbackInfo| {
+            let timestamp = info.timestamp();
+            let (first, first_at) = *first_capture.get_or_insert_with(|| {
+                let latency = timestamp
+                    .callback
+                    .duration_since(&timestamp.capture)
+                    .unwrap_or_default();
+                let now = Instant::now();
+                (timestamp.capture, now.checked_sub(latency).unwrap_or(now))
+            });
+            let captured_at =
+                first_at + timestamp.capture.duration_since(&first).unwrap_or_default();
+
+            scratch.clear();
+            scratch.extend(data.iter().map(|&sample| sample_to_f32(sample)));
+            on_data(&scratch, captured_at);
+        },
+        error_callback,
+        None,
+    )
+}
+
+/// Converts a device sample to [-1.0, 1.0]. Unsigned formats are centered on
+/// their midpoint, so silence maps to 0.0 for every format.
+pub fn sample_to_f32<T>(sample: T) -> f32
+where
+    T: cpal::Sample,
+    f32: cpal::FromSample<T>,
+{
+    sample.to_sample::<f32>()
+}
+
+impl SourceStream for cpal::Stream {
+    fn pause(&self) -> Result<(), String> {
+        StreamTrait::pause(self).map_err(|e| e.to_string())
+    }
+}
+
+/// Plays back a WAV file, or raw little-endian PCM16 with a given format.
+pub struct FileSource {
+    path: PathBuf,
+    raw_format: Option<(u32, u16)>,
+    samples: Vec<f32>,
+    format: Option<SourceFormat>,
+}
+
+impl FileSource {
+    /// A WAV file; the format is read from its header.
+    pub fn wav(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            raw_format: None,
+            samples: Vec::new(),
+            format: None,
+        }
+    }
+
+    /// Headerless interleaved PCM16.
+    pub fn raw_pcm16(path: impl Into<PathBuf>, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            raw_format: Some((sample_rate, channels)),
+            ..Self::wav(path)
+        }
+    }
+
+    fn read_wav(&self) -> Result<(Vec<f32>, u32, u16), String> {
+        let reader = hound::WavReader::open(&self.path)
+            .map_err(|e| format!("Failed to open {}: {}", self.path.display(), e))?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => {
+                reader.into_samples::<f32>().collect::<Result<Vec<_>, _>>()
+            }
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|sample| sample.map(|s| s as f32 / scale))
+                    .collect::<Result<Vec<_>, _>>()
+            }
+        }
+        .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+
+        Ok((samples, spec.sample_rate, spec.channels))
+    }
+
+    fn read_raw(&self, sample_rate: u32, channels: u16) -> Result<(Vec<f32>, u32, u16), String> {
+        let bytes = std::fs::read(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        let samples = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / i16::MAX as f32)
+            .collect();
+
+        Ok((samples, sample_rate, channels))
+    }
+}
+
+impl AudioSource for FileSource {
+    fn prepare(&mut self, _device: Option<&str>) -> Result<SourceFormat, String> {
+        let (samples, sample_rate, channels) = match self.raw_format {
+            Some((sample_rate, channels)) => self.read_raw(sample_rate, channels)?,
+            None => self.read_wav()?,
+        };
+
+        let format = SourceFormat {
+            device_name: self.path.display().to_string(),
+            sample_rate,
+            channels,
+            fallback_from: None,
+        };
+        self.samples = samples;
+        self.format = Some(format.clone());
+        Ok(format)
+    }
+
+    fn start(
+        &mut self,
+        on_data: SampleCallback,
+        _on_error: ErrorCallback,
+    ) -> Result<Box<dyn SourceStream>, String> {
+        let format = self
+            .format
+            .take()
+            .ok_or_else(|| "Audio source was not prepared".to_string())?;
+        let samples = std::mem::take(&mut self.samples);
+
+        let mut position = 0;
+        Ok(Box::new(GeneratorStream::spawn(
+            &format,
+            on_data,
+            move |block| {
+                let end = (position + block.len()).min(samples.len());
+                let filled = end - position;
+                block[..filled].copy_from_slice(&samples[position..end]);
+                position = end;
+                filled
+            },
+        )))
+    }
+}
+
+/// Generates a continuous sine tone, for exercising the pipeline without any
+/// audio input.
+pub struct ToneSource {
+    frequency: f32,
+    amplitude: f32,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl ToneSource {
+    pub fn new(frequency: f32) -> Self {
+        Self {
+            frequency,
+            amplitude: 0.5,
+            sample_rate: 48000,
+            channels: 1,
+        }
+    }
+
+    fn format(&self) -> SourceFormat {
+        SourceFormat {
+            device_name: format!("{} Hz tone", self.frequency),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            fallback_from: None,
+        }
+    }
+}
+
+impl AudioSource for ToneSource {
+    fn prepare(&mut self, _device: Option<&str>) -> Result<SourceFormat, String> {
+        Ok(self.format())
+    }
+
+    fn start(
+        &mut self,
+        on_data: SampleCallback,
+        _on_error: ErrorCallback,
+    ) -> Result<Box<dyn SourceStream>, String> {
+        let channels = self.channels.max(1) as usize;
+        let phase_step = 2.0 * PI * self.frequency / self.sample_rate as f32;
+        let amplitude = self.amplitude;
+        let mut phase = 0.0f32;
+        let mut channel = 0;
+
+        Ok(Box::new(GeneratorStream::spawn(
+            &self.format(),
+            on_data,
+            move |block| {
+                for sample in block.iter_mut() {
+                    *sample = amplitude * phase.sin();
+                    channel += 1;
+                    if channel == channels {
+                        channel = 0;
+                        phase = (phase + phase_step) % (2.0 * PI);
+                    }
+                }
+                block.len()
+            },
+        )))
+    }
+}
+
+/// Delivers generated audio from its own thread in 10ms blocks, paced like a
+/// device callback would be. Capture times follow the samples delivered.
+struct GeneratorStream {
+    running: Arc<AtomicBool>,
+}
+
+impl GeneratorStream {
+    /// `fill` writes the next samples into the block and returns how many it
+    /// wrote; fewer than requested ends the stream.
+    fn spawn(
+        format: &SourceFormat,
+        mut on_data: SampleCallback,
+        mut fill: impl FnMut(&mut [f32]) -> usize + Send + 'static,
+    ) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let block_len =
+            (format.sample_rate as usize / 100).max(1) * format.channels.max(1) as usize;
+
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let mut block = vec![0.0f32; block_len];
+            let mut blocks_sent = 0u32;
+
+            while thread_running.load(Ordering::SeqCst) {
+                let filled = fill(&mut block);
+                if filled > 0 {
+                    on_data(
+                        &block[..filled],
+                        started + Duration::from_millis(10) * blocks_sent,
+                    );
+                }
+                if filled < block_len {
+                    break;
+                }
+
+                blocks_sent += 1;
+                let due = started + Duration::from_millis(10) * blocks_sent;
+                if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                    std::thread::sleep(wait);
+                }
+            }
+        });
+
+        Self { running }
+    }
+}
+
+impl SourceStream for GeneratorStream {
+    fn pause(&self) -> Result<(), String> {
+        self.running.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for GeneratorStream {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Picks a source from `GORLAMI_AUDIO_SOURCE` so the app can run without a
+/// microphone: `wav:<path>`, `pcm:<path>:<rate>:<channels>` or `tone:<hz>`.
+/// Anything else, or the variable being unset, uses the input devices.
+pub fn source_from_env() -> Box<dyn AudioSource> {
+    let Ok(spec) = std::env::var("GORLAMI_AUDIO_SOURCE") else {
+        return Box::new(CpalSource::new());
+    };
+
+    match parse_source_spec(&spec) {
+        Some(source) => {
+            println!("Using audio source '{}'", spec);
+            source
+        }
+        None => {
+            eprintln!("Ignoring invalid GORLAMI_AUDIO_SOURCE '{}'", spec);
+            Box::new(CpalSource::new())
+        }
+    }
+}
+
+fn parse_source_spec(spec: &str) -> Option<Box<dyn AudioSource>> {
+    let (kind, rest) = spec.split_once(':')?;
+    match kind {
+        "wav" => Some(Box::new(FileSource::wav(rest))),
+        "pcm" => {
+            let mut parts = rest.rsplitn(3, ':');
+            let channels = parts.next()?.parse().ok()?;
+            let sample_rate = parts.next()?.parse().ok()?;
+            let path = parts.next()?;
+            Some(Box::new(FileSource::raw_pcm16(path, sample_rate, channels)))
+        }
+        "tone" => Some(Box::new(ToneSource::new(rest.parse().ok()?))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+    use uuid::Uuid;
+
+    fn temp_path(extension: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gorlami-source-{}.{}", Uuid::new_v4(), extension))
+    }
+
+    /// Starts a prepared source, forwarding every delivered block.
+    fn start(source: &mut dyn AudioSource) -> (Box<dyn SourceStream>, Receiver<Vec<f32>>) {
+        let (blocks, received) = channel();
+        let stream = source
+            .start(
+                Box::new(move |data: &[f32], _| {
+                    let _ = blocks.send(data.to_vec());
+                }),
+                Box::new(|err: String| panic!("source error: {}", err)),
+            )
+            .unwrap();
+        (stream, received)
+    }
+
+    /// Blocks delivered until the source ends on its own.
+    fn drain(received: &Receiver<Vec<f32>>) -> Vec<Vec<f32>> {
+        let mut blocks = Vec::new();
+        loop {
+            match received.recv_timeout(Duration::from_secs(5)) {
+                Ok(block) => blocks.push(block),
+                Err(RecvTimeoutError::Disconnected) => return blocks,
+                Err(RecvTimeoutError::Timeout) => panic!("source did not end"),
+            }
+        }
+    }
+
+    #[test]
+    fn file_source_plays_a_wav_file_to_the_end() {
+        let path = temp_path("wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let samples: Vec<i16> = (0..250).map(|i| (i * 100 - 12500) as i16).collect();
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &sample in &samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut source = FileSource::wav(&path);
+        let format = source.prepare(None).unwrap();
+        assert_eq!((format.sample_rate, format.channels), (8000, 1));
+
+        let (_stream, received) = start(&mut source);
+        let blocks = drain(&received);
+        let _ = std::fs::remove_file(&path);
+
+        // 10ms blocks, then whatever is left
+        let lengths: Vec<usize> = blocks.iter().map(Vec::len).collect();
+        assert_eq!(lengths, vec![80, 80, 80, 10]);
+        let played: Vec<f32> = blocks.concat();
+        for (&actual, &sample) in played.iter().zip(&samples) {
+            assert_close(actual, sample as f32 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn file_source_reads_raw_interleaved_pcm16() {
+        let path = temp_path("pcm");
+        let samples: Vec<i16> = (0..400)
+            .map(|i| if i % 2 == 0 { 16384 } else { -16384 })
+            .collect();
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut source = FileSource::raw_pcm16(&path, 16000, 2);
+        let format = source.prepare(None).unwrap();
+        assert_eq!((format.sample_rate, format.channels), (16000, 2));
+
+        let (_stream, received) = start(&mut source);
+        let blocks = drain(&received);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(blocks[0].len(), 320);
+        let played: Vec<f32> = blocks.concat();
+        assert_eq!(played.len(), samples.len());
+        for pair in played.chunks(2) {
+            assert_close(pair[0], 0.5);
+            assert_close(pair[1], -0.5);
+        }
+    }
+
+    #[test]
+    fn file_source_must_be_prepared_first() {
+        let mut source = FileSource::wav(temp_path("wav"));
+        assert!(source.prepare(None).is_err());
+        assert!(source
+            .start(Box::new(|_: &[f32], _| {}), Box::new(|_: String| {}))
+            .is_err());
+    }
+
+    #[test]
+    fn tone_source_generates_the_requested_frequency() {
+        let mut source = ToneSource::new(1000.0);
+        let format = source.prepare(Some("ignored")).unwrap();
+        assert_eq!((format.sample_rate, format.channels), (48000, 1));
+
+        let (stream, received) = start(&mut source);
+        let blocks: Vec<Vec<f32>> = (0..10)
+            .map(|_| received.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        stream.pause().unwrap();
+
+        assert!(blocks.iter().all(|block| block.len() == 480));
+        let tone = blocks.concat();
+        let peak = tone.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert_close(peak, 0.5);
+
+        // Two zero crossings per cycle: 100 cycles in 100ms
+        let crossings = tone
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        assert!((198..=202).contains(&crossings), "{} crossings", crossings);
+    }
+
+    #[test]
+    fn generator_stream_is_paced_and_stops_on_pause() {
+        let format = SourceFormat {
+            device_name: "generator".to_string(),
+            sample_rate: 1000,
+            channels: 2,
+            fallback_from: None,
+        };
+        let (blocks, received) = channel();
+        let started = Instant::now();
+        let stream = GeneratorStream::spawn(
+            &format,
+            Box::new(move |data: &[f32], captured_at| {
+                let _ = blocks.send((data.len(), captured_at));
+            }),
+            |block| {
+                block.fill(0.25);
+                block.len()
+            },
+        );
+
+        // 10 frames of 2 channels every 10ms, in real time
+        let (len, first) = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(len, 20);
+        assert!(first >= started);
+        for block in 1..10 {
+            let (len, captured_at) = received.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(len, 20);
+            assert_eq!(captured_at - first, Duration::from_millis(10) * block);
+        }
+        assert!(started.elapsed() >= Duration::from_millis(90));
+
+        stream.pause().unwrap();
+        let mut after_pause = 0;
+        while received.recv_timeout(Duration::from_secs(5)).is_ok() {
+            after_pause += 1;
+        }
+        assert!(after_pause <= 1);
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn i8_maps_to_unit_range() {
+        assert_close(sample_to_f32(i8::MIN), -1.0);
+        assert_close(sample_to_f32(0i8), 0.0);
+        assert_close(sample_to_f32(i8::MAX), 1.0 - 1.0 / 128.0);
+    }
+
+    #[test]
+    fn i16_maps_to_unit_range() {
+        assert_close(sample_to_f32(i16::MIN), -1.0);
+        assert_close(sample_to_f32(0i16), 0.0);
+        assert_close(sample_to_f32(i16::MAX), 1.0);
+        assert_close(sample_to_f32(16384i16), 0.5);
+    }
+
+    #[test]
+    fn i24_maps_to_unit_range() {
+        let min = cpal::I24::new(-(1 << 23)).unwrap();
+        let max = cpal::I24::new((1 << 23) - 1).unwrap();
+        assert_close(sample_to_f32(min), -1.0);
+        assert_close(sample_to_f32(cpal::I24::new(0).unwrap()), 0.0);
+        assert_close(sample_to_f32(max), 1.0);
+    }
+
+    #[test]
+    fn i32_maps_to_unit_range() {
+        assert_close(sample_to_f32(i32::MIN), -1.0);
+        assert_close(sample_to_f32(0i32), 0.0);
+        assert_close(sample_to_f32(i32::MAX), 1.0);
+    }
+
+    #[test]
+    fn i64_maps_to_unit_range() {
+        assert_close(sample_to_f32(i64::MIN), -1.0);
+        assert_close(sample_to_f32(0i64), 0.0);
+        assert_close(sample_to_f32(i64::MAX), 1.0);
+    }
+
+    #[test]
+    fn u8_is_centered_on_midpoint() {
+        assert_close(sample_to_f32(u8::MIN), -1.0);
+        assert_close(sample_to_f32(128u8), 0.0);
+        assert_close(sample_to_f32(u8::MAX), 1.0 - 1.0 / 128.0);
+    }
+
+    #[test]
+    fn u16_is_centered_on_midpoint() {
+        assert_close(sample_to_f32(u16::MIN), -1.0);
+        assert_close(sample_to_f32(32768u16), 0.0);
+        assert_close(sample_to_f32(u16::MAX), 1.0);
+    }
+
+    #[test]
+    fn u32_is_centered_on_midpoint() {
+        assert_close(sample_to_f32(u32::MIN), -1.0);
+        assert_close(sample_to_f32(1u32 << 31), 0.0);
+        assert_close(sample_to_f32(u32::MAX), 1.0);
+    }
+
+    #[test]
+    fn u64_is_centered_on_midpoint() {
+        assert_close(sample_to_f32(u64::MIN), -1.0);
+        assert_close(sample_to_f32(1u64 << 63), 0.0);
+        assert_close(sample_to_f32(u64::MAX), 1.0);
+    }
+
+    #[test]
+    fn floats_pass_through() {
+        for value in [-1.0f32, -0.25, 0.0, 0.5, 1.0] {
+            assert_eq!(sample_to_f32(value), value);
+            assert_eq!(sample_to_f32(value as f64), value);
+        }
+    }
+
+    /// The old U16 path computed `sample / u16::MAX - 0.5`, which only spans
+    /// [-0.5, 0.5]: full-scale input came out 6 dB too quiet.
+    #[test]
+    fn legacy_u16_normalization_halved_the_signal() {
+        let legacy = |sample: u16| (sample as f32 / u16::MAX as f32) - 0.5;
+
+        assert_close(legacy(u16::MIN), -0.5);
+        assert_close(legacy(u16::MAX), 0.5);
+        assert_close(sample_to_f32(u16::MIN), -1.0);
+        assert_close(sample_to_f32(u16::MAX), 1.0);
+    }
+
+    /// The old I16 path divided by `i16::MAX`, which pushed `i16::MIN` just below -1.0.
+    #[test]
+    fn i16_min_stays_in_range() {
+        let legacy = i16::MIN as f32 / i16::MAX as f32;
+
+        assert!(legacy < -1.0);
+        assert!(sample_to_f32(i16::MIN) >= -1.0);
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Samples kept in memory before the session spills to a temp file (~4 minutes at 16 kHz).
+const MEMORY_LIMIT_SAMPLES: usize = 4 * 1024 * 1024;
+
+/// Samples per in-memory block, so appends never move earlier audio.
+const BLOCK_SAMPLES: usize = 16 * 1024;
+
+/// Unbounded storage for the PCM16 samples of one recording session.
+///
+/// The samples are owned by a writer thread, so the capture callback only
+/// hands them over a channel and never waits on a lock or the disk. Audio is
+/// kept in fixed-size blocks until the memory limit is reached, after which
+/// everything is moved to a temp file and later samples are appended there.
+#[derive(Clone)]
+pub struct CaptureStore {
+    commands: Sender<StoreCommand>,
+    total: Arc<AtomicUsize>,
+}
+
+enum StoreCommand {
+    Append(Vec<i16>),
+    Silence(usize),
+    TakeAll(Sender<io::Result<Vec<i16>>>),
+    Clear,
+}
+
+impl CaptureStore {
+    pub fn new() -> Self {
+        Self::with_memory_limit(MEMORY_LIMIT_SAMPLES)
+    }
+
+    pub fn with_memory_limit(memory_limit: usize) -> Self {
+        let (commands, receiver) = channel();
+        std::thread::Builder::new()
+            .name("capture-store".to_string())
+            .spawn(move || run_writer(Storage::new(memory_limit), receiver))
+            .expect("Failed to spawn capture store thread");
+
+        Self {
+            commands,
+            total: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of samples captured so far.
+    pub fn len(&self) -> usize {
+        self.total.load(Ordering::SeqCst)
+    }
+
+    /// Hands samples to the writer thread. Write errors are logged there.
+    pub fn append(&self, samples: &[i16]) {
+        if samples.is_empty() {
+            return;
+        }
+        self.total.fetch_add(samples.len(), Ordering::SeqCst);
+        let _ = self.commands.send(StoreCommand::Append(samples.to_vec()));
+    }
+
+    /// Adds `len` zero samples, standing in for audio the input lost.
+    pub fn append_silence(&self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        self.total.fetch_add(len, Ordering::SeqCst);
+        let _ = self.commands.send(StoreCommand::Silence(len));
+    }
+
+    /// Returns the whole session in order and resets the store. Waits for
+    /// every earlier append to be stored first.
+    pub fn take_all(&self) -> io::Result<Vec<i16>> {
+        let (reply, response) = channel();
+        self.commands
+            .send(StoreCommand::TakeAll(reply))
+            .map_err(|_| io::Error::other("Capture store thread has stopped"))?;
+        self.total.store(0, Ordering::SeqCst);
+        response
+            .recv()
+            .map_err(|_| io::Error::other("Capture store thread has stopped"))?
+    }
+
+    /// Drops all captured audio, including any temp file.
+    pub fn clear(&self) {
+        self.total.store(0, Ordering::SeqCst);
+        let _ = self.commands.send(StoreCommand::Clear);
+    }
+}
+
+impl Default for CaptureStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Applies store commands until every handle is dropped.
+fn run_writer(mut storage: Storage, commands: Receiver<StoreCommand>) {
+    while let Ok(command) = commands.recv() {
+        match command {
+            StoreCommand::Append(samples) => {
+                if let Err(e) = storage.append(&samples) {
+                    eprintln!("Failed to store captured audio: {}", e);
+                }
+            }
+            StoreCommand::Silence(len) => {
+                let silence = [0i16; BLOCK_SAMPLES];
+                let mut remaining = len;
+                while remaining > 0 {
+                    let take = remaining.min(BLOCK_SAMPLES);
+                    if let Err(e) = storage.append(&silence[..take]) {
+                        eprintln!("Failed to store captured audio: {}", e);
+                        break;
+                    }
+                    remaining -= take;
+                }
+            }
+            StoreCommand::TakeAll(reply) => {
+                let _ = reply.send(storage.take_all());
+            }
+            StoreCommand::Clear => storage.clear(),
+        }
+    }
+}
+
+/// The samples themselves, owned by the writer thread.
+struct Storage {
+    blocks: Vec<Vec<i16>>,
+    in_memory: usize,
+    memory_limit: usize,
+    spill: Option<SpillFile>,
+}
+
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Storage {
+    fn new(memory_limit: usize) -> Self {
+        Self {
+            blocks: Vec::new(),
+            in_memory: 0,
+            memory_limit,
+            spill: None,
+        }
+    }
+
+    fn append(&mut self, samples: &[i16]) -> io::Result<()> {
+        if let Some(spill) = self.spill.as_mut() {
+            return write_samples(&mut spill.writer, samples);
+        }
+
+        let mut remaining = samples;
+        while !remaining.is_empty() {
+            if self.blocks.last().is_none_or(|b| b.len() == BLOCK_SAMPLES) {
+                self.blocks.push(Vec::with_capacity(BLOCK_SAMPLES));
+            }
+            let block = self.blocks.last_mut().unwrap();
+            let take = remaining.len().min(BLOCK_SAMPLES - block.len());
+            block.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+        }
+        self.in_memory += samples.len();
+
+        if self.in_memory > self.memory_limit {
+            self.spill_to_disk()?;
+        }
+
+        Ok(())
+    }
+
+    fn take_all(&mut self) -> io::Result<Vec<i16>> {
+        let mut samples = Vec::with_capacity(self.in_memory);
+
+        if let Some(mut spill) = self.spill.take() {
+            spill.writer.flush()?;
+            let mut bytes = Vec::new();
+            File::open(&spill.path)?.read_to_end(&mut bytes)?;
+            let _ = fs::remove_file(&spill.path);
+            samples.extend(
+                bytes
+                    .chunks_exact(2)
+                    .map(|pair| i16::from_le_bytes([pair[0], pair[1]])),
+            );
+        }
+
+        for block in self.blocks.drain(..) {
+            samples.extend_from_slice(&block);
+        }
+
+        self.in_memory = 0;
+        Ok(samples)
+    }
+
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.in_memory = 0;
+        if let Some(spill) = self.spill.take() {
+            drop(spill.writer);
+            let _ = fs::remove_file(&spill.path);
+        }
+    }
+
+    fn spill_to_disk(&mut self) -> io::Result<()> {
+        let path = std::env::temp_dir().join(format!("gorlami-capture-{}.pcm", Uuid::new_v4()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+
+        for block in self.blocks.drain(..) {
+            write_samples(&mut writer, &block)?;
+        }
+        self.in_memory = 0;
+
+        println!(
+            "Recording exceeded memory limit, spilling to {}",
+            path.display()
+        );
+        self.spill = Some(SpillFile { path, writer });
+        Ok(())
+    }
+}
+
+impl Drop for Storage {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn write_samples(writer: &mut impl Write, samples: &[i16]) -> io::Result<()> {
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(start: usize, len: usize) -> Vec<i16> {
+        (start..start + len).map(|i| i as i16).collect()
+    }
+
+    #[test]
+    fn keeps_short_sessions_in_memory() {
+        let mut storage = Storage::new(MEMORY_LIMIT_SAMPLES);
+        storage.append(&ramp(0, BLOCK_SAMPLES + 10)).unwrap();
+        storage.append(&ramp(BLOCK_SAMPLES + 10, 5)).unwrap();
+
+        assert!(storage.spill.is_none());
+        assert_eq!(storage.blocks.len(), 2);
+        assert_eq!(storage.take_all().unwrap(), ramp(0, BLOCK_SAMPLES + 15));
+        assert!(storage.take_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn spills_past_the_memory_limit_and_reads_back_in_order() {
+        let mut storage = Storage::new(100);
+        storage.append(&ramp(0, 80)).unwrap();
+        assert!(storage.spill.is_none());
+
+        storage.append(&ramp(80, 40)).unwrap();
+        let path = storage.spill.as_ref().unwrap().path.clone();
+        assert!(path.exists());
+        assert!(storage.blocks.is_empty());
+
+        storage.append(&ramp(120, 30)).unwrap();
+        assert_eq!(storage.take_all().unwrap(), ramp(0, 150));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn clear_removes_the_spill_file() {
+        let mut storage = Storage::new(10);
+        storage.append(&ramp(0, 20)).unwrap();
+        let path = storage.spill.as_ref().unwrap().path.clone();
+
+        storage.clear();
+        assert!(!path.exists());
+        assert!(storage.take_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn writer_thread_stores_appends_in_order() {
+        let store = CaptureStore::with_memory_limit(64);
+        for start in (0..500).step_by(50) {
+            store.append(&ramp(start, 50));
+        }
+        assert_eq!(store.len(), 500);
+
+        assert_eq!(store.take_all().unwrap(), ramp(0, 500));
+        assert_eq!(store.len(), 0);
+
+        store.append(&ramp(0, 10));
+        store.clear();
+        store.append(&ramp(7, 3));
+        assert_eq!(store.take_all().unwrap(), ramp(7, 3));
+
+        store.append(&ramp(0, 5));
+        store.append_silence(100);
+        store.append(&ramp(5, 5));
+        assert_eq!(store.len(), 110);
+        let samples = store.take_all().unwrap();
+        assert_eq!(samples[..5], ramp(0, 5)[..]);
+        assert!(samples[5..105].iter().all(|&s| s == 0));
+        assert_eq!(samples[105..], ramp(5, 5)[..]);
+    }
+}
@@ -0,0 +1,827 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::websocket::ProtocolMessage;
+
+/// What happens to audio when the outbound queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued chunk, keeping the most recent speech.
+    #[default]
+    DropOldest,
+    /// Drop new chunks, keeping the start of the dictation.
+    DropNewest,
+}
+
+/// Limits of the queue that holds messages for the backend, including while
+/// the connection is down.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundQueueConfig {
+    /// Messages held in memory, about 100ms of audio each.
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    /// Spill audio beyond `capacity` to a file instead of dropping it.
+    pub spool_to_disk: bool,
+    /// Spooled audio beyond which the overflow policy applies. The spool
+    /// file is compacted as it is read, so it stays under about twice this.
+    pub max_spool_mb: u64,
+}
+
+impl Default for OutboundQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 600,
+            overflow: OverflowPolicy::DropOldest,
+            spool_to_disk: false,
+            max_spool_mb: 100,
+        }
+    }
+}
+
+/// Queue state reported with the connection status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueStatus {
+    /// Messages waiting, in memory and on disk.
+    pub depth: usize,
+    /// Of those, messages spooled to disk.
+    pub spooled: usize,
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    /// Chunks dropped by the overflow policy since the last connection.
+    pub dropped: u64,
+}
+
+/// Where a chunk belongs in its recording session. Written in front of the
+/// PCM16 payload of every streamed frame so the backend can order chunks,
+/// notice missing ones and line up word timings with the audio.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkHeader {
+    pub session_id: Uuid,
+    /// Counts up from 0 for each session.
+    pub sequence: u64,
+    /// Sample frames between the start of the session and this chunk.
+    pub sample_offset: u64,
+}
+
+impl ChunkHeader {
+    /// Marks a frame as carrying a header, and its layout version.
+    pub const MAGIC: &'static [u8; 4] = b"GRL1";
+    /// Magic, 16 byte session UUID, then sequence and sample offset as
+    /// little-endian u64.
+    pub const LEN: usize = 4 + 16 + 8 + 8;
+
+    pub fn encode(&self, pcm: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(Self::LEN + pcm.len());
+        frame.extend_from_slice(Self::MAGIC);
+        frame.extend_from_slice(self.session_id.as_bytes());
+        frame.extend_from_slice(&self.sequence.to_le_bytes());
+        frame.extend_from_slice(&self.sample_offset.to_le_bytes());
+        frame.extend_from_slice(pcm);
+        frame
+    }
+}
+
+/// Bounded hand-off of encoded audio chunks from the capture thread to the
+/// WebSocket writer, so streamed audio never goes through the event bus.
+/// Session control messages share the queue so they stay in order with the
+/// audio around them.
+///
+/// Messages are kept while the connection is down and replayed once it is
+/// back. Pushing never blocks the audio callback on the disk: audio beyond
+/// the capacity is handed to a spool thread that writes it to a file, if
+/// enabled, or dropped under the overflow policy; control messages are never
+/// dropped. The writer reads the file back.
+#[derive(Clone)]
+pub struct AudioChunkQueue {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    /// The spool file. Never locked by `push`, and locked before `state`
+    /// when both are needed.
+    spool: Mutex<Option<Spool>>,
+    spool_path: Option<PathBuf>,
+    /// Wakes the spool thread, which runs when there is a spool path.
+    spooler: Option<Sender<()>>,
+    notify: Notify,
+}
+
+struct State {
+    messages: VecDeque<ProtocolMessage>,
+    /// Messages behind the spool file, waiting for the spool thread to
+    /// write them.
+    incoming: VecDeque<ProtocolMessage>,
+    /// Messages behind `messages`, in the spool file or in `incoming`.
+    spooled: usize,
+    /// Bytes of audio among them, which `max_spool_mb` limits.
+    spooled_bytes: u64,
+    /// Spooled messages to move up into memory, in place of the oldest
+    /// chunks dropped to make room.
+    promote: usize,
+    config: OutboundQueueConfig,
+    enabled: bool,
+    dropped: u64,
+    /// Start of the session the backend has not seen the end of yet.
+    open_session: Option<OpenSession>,
+}
+
+struct OpenSession {
+    session_id: String,
+    start: ProtocolMessage,
+    /// The start was written to the current connection.
+    sent: bool,
+}
+
+impl AudioChunkQueue {
+    /// Audio that does not fit in memory is spooled to `spool_path` when
+    /// `spool_to_disk` is enabled.
+    pub fn new(config: OutboundQueueConfig, spool_path: Option<PathBuf>) -> Self {
+        let (spooler, wake) = match spool_path {
+            Some(_) => {
+                let (spooler, wake) = channel();
+                (Some(spooler), Some(wake))
+            }
+            None => (None, None),
+        };
+        let queue = Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    messages: VecDeque::new(),
+                    incoming: VecDeque::new(),
+                    spooled: 0,
+                    spooled_bytes: 0,
+                    promote: 0,
+                    config,
+                    enabled: false,
+                    dropped: 0,
+                    open_session: None,
+                }),
+                spool: Mutex::new(None),
+                spool_path,
+                spooler,
+                notify: Notify::new(),
+            }),
+        };
+
+        if let Some(wake) = wake {
+            let inner = Arc::downgrade(&queue.inner);
+            std::thread::Builder::new()
+                .name("chunk-spool".to_string())
+                .spawn(move || run_spooler(inner, wake))
+                .expect("Failed to spawn spool thread");
+        }
+        queue
+    }
+
+    pub fn configure(&self, config: OutboundQueueConfig) {
+        self.inner.state.lock().unwrap().config = config;
+    }
+
+    /// Queues a message for the writer. Returns false if it was dropped, or
+    /// if there is no connection it could be sent on.
+    pub fn push(&self, message: ProtocolMessage) -> bool {
+        let (queued, spool) = {
+            let mut state = self.inner.state.lock().unwrap();
+            if !state.enabled {
+                return false;
+            }
+            if let ProtocolMessage::SessionStart { session_id, .. } = &message {
+                state.open_session = Some(OpenSession {
+                    session_id: session_id.clone(),
+                    start: message.clone(),
+                    sent: false,
+                });
+            }
+            let queued = state.enqueue(message, self.inner.spooler.is_some());
+            (queued, !state.incoming.is_empty() || state.promote > 0)
+        };
+
+        if spool {
+            if let Some(spooler) = &self.inner.spooler {
+                let _ = spooler.send(());
+            }
+        }
+        self.inner.notify.notify_one();
+        queued
+    }
+
+    /// Waits for the next message. Safe to use as a `select!` branch: a
+    /// message stays queued until it is returned.
+    pub async fn pop(&self) -> ProtocolMessage {
+        loop {
+            if let Some(message) = self.inner.next() {
+                return message;
+            }
+            self.inner.notify.notified().await;
+        }
+    }
+
+    /// Puts back a message that could not be written, ahead of the rest.
+    pub fn requeue(&self, message: ProtocolMessage) {
+        let mut state = self.inner.state.lock().unwrap();
+        if let (ProtocolMessage::SessionStart { .. }, Some(open)) =
+            (&message, state.open_session.as_mut())
+        {
+            open.sent = false;
+        }
+        state.messages.push_front(message);
+    }
+
+    /// Starts or stops keeping messages for the backend. Anything still
+    /// queued is thrown away when stopping.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut spool = self.inner.spool.lock().unwrap();
+        let mut state = self.inner.state.lock().unwrap();
+        state.enabled = enabled;
+        if !enabled {
+            state.messages.clear();
+            state.incoming.clear();
+            state.spooled = 0;
+            state.spooled_bytes = 0;
+            state.promote = 0;
+            state.open_session = None;
+            if let Some(spool) = spool.as_mut() {
+                if let Err(e) = spool.clear() {
+                    eprintln!("Failed to clear spool file: {}", e);
+                }
+            }
+        }
+        state.dropped = 0;
+    }
+
+    /// Prepares the queue for a new connection. The backend does not know a
+    /// session started on the previous one, so its start is sent again
+    /// before the rest of its audio.
+    pub fn resume(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        let restart = state
+            .open_session
+            .as_mut()
+            .filter(|open| open.sent)
+            .map(|open| {
+                open.sent = false;
+                open.start.clone()
+            });
+        if let Some(start) = restart {
+            state.messages.push_front(start);
+        }
+        state.dropped = 0;
+    }
+
+    /// Messages waiting to be written.
+    pub fn depth(&self) -> usize {
+        self.inner.state.lock().unwrap().depth()
+    }
+
+    /// Chunks dropped since the last connection because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.inner.state.lock().unwrap().dropped
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let state = self.inner.state.lock().unwrap();
+        QueueStatus {
+            depth: state.depth(),
+            spooled: state.spooled,
+            capacity: state.config.capacity,
+            overflow: state.config.overflow,
+            dropped: state.dropped,
+        }
+    }
+}
+
+impl Inner {
+    /// Takes the next message to write, oldest first, reading the spool file
+    /// if it comes from there.
+    fn next(&self) -> Option<ProtocolMessage> {
+        let mut spool = self.spool.lock().unwrap();
+        let message = {
+            let mut state = self.state.lock().unwrap();
+            match state.messages.pop_front() {
+                Some(message) => Some(message),
+                None if state.spilling() => None,
+                None => return None,
+            }
+        };
+        let message = message.or_else(|| self.take_spooled(&mut spool))?;
+        Some(self.state.lock().unwrap().taken(message))
+    }
+
+    /// Takes the oldest message behind `messages`: from the spool file, or
+    /// from `incoming` if the file has been read to the end.
+    fn take_spooled(&self, spool: &mut Option<Spool>) -> Option<ProtocolMessage> {
+        if let Some(file) = spool.as_mut().filter(|file| file.count > 0) {
+            match file.take() {
+                Ok(Some(message)) => {
+                    self.state.lock().unwrap().unspool(&message);
+                    return Some(message);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!(
+                        "Failed to read spooled message, discarding the spool: {}",
+                        e
+                    );
+                    let _ = file.clear();
+                    let mut state = self.state.lock().unwrap();
+                    state.spooled = state.incoming.len();
+                    state.spooled_bytes = state.incoming.iter().map(spooled_size).sum();
+                }
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let message = state.incoming.pop_front()?;
+        state.unspool(&message);
+        Some(message)
+    }
+
+    /// Writes the messages waiting in `incoming` to the spool file, after
+    /// moving up the ones that replace dropped chunks.
+    fn spool_incoming(&self) -> io::Result<()> {
+        let Some(path) = self.spool_path.as_deref() else {
+            return Ok(());
+        };
+        let mut spool = self.spool.lock().unwrap();
+
+        let promote = std::mem::take(&mut self.state.lock().unwrap().promote);
+        for _ in 0..promote {
+            let Some(message) = self.take_spooled(&mut spool) else {
+                break;
+            };
+            self.state.lock().unwrap().messages.push_back(message);
+        }
+
+        if self.state.lock().unwrap().incoming.is_empty() {
+            return Ok(());
+        }
+        if spool.is_none() {
+            *spool = Some(Spool::create(path)?);
+        }
+        let file = spool.as_mut().unwrap();
+
+        let (incoming, limit) = {
+            let mut state = self.state.lock().unwrap();
+            let incoming: Vec<_> = state.incoming.drain(..).collect();
+            (incoming, state.config.max_spool_mb * 1024 * 1024)
+        };
+        let mut incoming = incoming.into_iter();
+        while let Some(message) = incoming.next() {
+            if let Err(e) = file.append(&message, limit) {
+                // Keep the rest in memory, still behind the file
+                let mut state = self.state.lock().unwrap();
+                for message in std::iter::once(message).chain(incoming).rev() {
+                    state.incoming.push_front(message);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spools messages whenever `push` asks for it, until the queue is dropped.
+fn run_spooler(inner: Weak<Inner>, wake: Receiver<()>) {
+    let mut failing = false;
+    while wake.recv().is_ok() {
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+        match inner.spool_incoming() {
+            Ok(()) => failing = false,
+            Err(e) => {
+                if !failing {
+                    eprintln!("Failed to spool messages to disk: {}", e);
+                }
+                failing = true;
+            }
+        }
+        inner.notify.notify_one();
+    }
+}
+
+impl State {
+    fn depth(&self) -> usize {
+        self.messages.len() + self.spooled
+    }
+
+    /// Messages are being spooled, so new ones go there too to keep order.
+    fn spilling(&self) -> bool {
+        self.spooled > 0
+    }
+
+    /// Adds a message behind everything queued, making room under the
+    /// overflow policy if needed. Returns false if the message was dropped.
+    /// Only touches memory; the spool thread writes spooled messages.
+    fn enqueue(&mut self, message: ProtocolMessage, can_spool: bool) -> bool {
+        let is_audio = matches!(message, ProtocolMessage::Audio(_));
+        let memory_full = self.messages.len() >= self.config.capacity.max(1);
+        if !self.spilling() && (!is_audio || !memory_full) {
+            self.messages.push_back(message);
+            return true;
+        }
+
+        let spool_open = self.spilling() || (self.config.spool_to_disk && can_spool);
+        let limit = self.config.max_spool_mb * 1024 * 1024;
+        if spool_open && (!is_audio || self.spooled_bytes + spooled_size(&message) <= limit) {
+            self.spool(message);
+            return true;
+        }
+
+        self.dropped += 1;
+        if self.dropped == 1 || self.dropped.is_multiple_of(50) {
+            eprintln!(
+                "Outbound queue is full, dropped {} chunk(s) ({:?})",
+                self.dropped, self.config.overflow
+            );
+        }
+        match self.config.overflow {
+            OverflowPolicy::DropNewest => false,
+            OverflowPolicy::DropOldest => {
+                let Some(index) = self
+                    .messages
+                    .iter()
+                    .position(|queued| matches!(queued, ProtocolMessage::Audio(_)))
+                else {
+                    return false;
+                };
+                self.messages.remove(index);
+                if self.spilling() {
+                    // Move the oldest spooled message up to keep both limits
+                    self.promote += 1;
+                    self.spool(message);
+                } else {
+                    self.messages.push_back(message);
+                }
+                true
+            }
+        }
+    }
+
+    /// Leaves a message for the spool thread.
+    fn spool(&mut self, message: ProtocolMessage) {
+        self.spooled += 1;
+        self.spooled_bytes += spooled_size(&message);
+        self.incoming.push_back(message);
+    }
+
+    /// Accounts for a message taken from behind `messages`.
+    fn unspool(&mut self, message: &ProtocolMessage) {
+        self.spooled -= 1;
+        self.spooled_bytes -= spooled_size(message);
+    }
+
+    /// Notes a message that is about to be written.
+    fn taken(&mut self, message: ProtocolMessage) -> ProtocolMessage {
+        let open = self.open_session.as_mut();
+        match (&message, open) {
+            (ProtocolMessage::SessionStart { session_id, .. }, Some(open))
+                if *session_id == open.session_id =>
+            {
+                open.sent = true;
+            }
+            (ProtocolMessage::SessionEnd { session_id, .. }, Some(open))
+                if *session_id == open.session_id =>
+            {
+                self.open_session = None;
+            }
+            _ => {}
+        }
+        message
+    }
+}
+
+/// Spooled messages are a kind byte, then the payload length as
+/// little-endian u32 and the payload: PCM frames for audio, JSON for
+/// control messages.
+const SPOOL_AUDIO: u8 = 0;
+const SPOOL_CONTROL: u8 = 1;
+const SPOOL_RECORD_HEADER: u64 = 5;
+
+/// Bytes a message counts against `max_spool_mb`. Control messages are
+/// never dropped, so only audio counts.
+fn spooled_size(message: &ProtocolMessage) -> u64 {
+    match message {
+        ProtocolMessage::Audio(frame) => SPOOL_RECORD_HEADER + frame.len() as u64,
+        _ => 0,
+    }
+}
+
+/// Messages spilled to a file, read back in the order they were written.
+/// The file is truncated whenever it has been read to the end, and the part
+/// already read is dropped once it is as large as the rest.
+struct Spool {
+    file: File,
+    /// Where the next message to read starts.
+    read_at: u64,
+    /// End of the last message written.
+    write_at: u64,
+    count: usize,
+}
+
+impl Spool {
+    fn create(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(Self {
+            file,
+            read_at: 0,
+            write_at: 0,
+            count: 0,
+        })
+    }
+
+    /// Appends a message, first compacting the file if it would grow past
+    /// `limit` bytes and at least half of it has been read.
+    fn append(&mut self, message: &ProtocolMessage, limit: u64) -> io::Result<()> {
+        let (kind, payload) = match message {
+            ProtocolMessage::Audio(frame) => (SPOOL_AUDIO, Cow::Borrowed(frame.as_slice())),
+            control => (SPOOL_CONTROL, Cow::Owned(serde_json::to_vec(control)?)),
+        };
+
+        let record = SPOOL_RECORD_HEADER + payload.len() as u64;
+        if self.write_at + record > limit && self.read_at >= self.write_at - self.read_at {
+            self.compact()?;
+        }
+
+        self.file.seek(SeekFrom::Start(self.write_at))?;
+        self.file.write_all(&[kind])?;
+        self.file.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.file.write_all(&payload)?;
+        self.write_at += record;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn take(&mut self) -> io::Result<Option<ProtocolMessage>> {
+        if self.count == 0 {
+            return Ok(None);
+        }
+
+        self.file.seek(SeekFrom::Start(self.read_at))?;
+        let mut header = [0u8; SPOOL_RECORD_HEADER as usize];
+        self.file.read_exact(&mut header)?;
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let mut payload = vec![0; len];
+        self.file.read_exact(&mut payload)?;
+        self.read_at += SPOOL_RECORD_HEADER + len as u64;
+        self.count -= 1;
+        if self.count == 0 {
+            self.clear()?;
+        }
+
+        let message = match header[0] {
+            SPOOL_AUDIO => ProtocolMessage::Audio(payload),
+            _ => serde_json::from_slice(&payload)?,
+        };
+        Ok(Some(message))
+    }
+
+    /// Moves the unread messages to the start of the file and cuts off the
+    /// rest.
+    fn compact(&mut self) -> io::Result<()> {
+        let mut buffer = vec![0; 64 * 1024];
+        let mut from = self.read_at;
+        let mut to = 0;
+        while from < self.write_at {
+            let len = buffer.len().min((self.write_at - from) as usize);
+            self.file.seek(SeekFrom::Start(from))?;
+            self.file.read_exact(&mut buffer[..len])?;
+            self.file.seek(SeekFrom::Start(to))?;
+            self.file.write_all(&buffer[..len])?;
+            from += len as u64;
+            to += len as u64;
+        }
+
+        self.write_at -= self.read_at;
+        self.read_at = 0;
+        self.file.set_len(self.write_at)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.read_at = 0;
+        self.write_at = 0;
+        self.count = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Removes the spool file when the test ends.
+    struct TempSpool(PathBuf);
+
+    impl TempSpool {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("gorlami-spool-{}.spool", Uuid::new_v4())))
+        }
+
+        fn len(&self) -> u64 {
+            fs::metadata(&self.0).map_or(0, |metadata| metadata.len())
+        }
+    }
+
+    impl Drop for TempSpool {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn queue(config: OutboundQueueConfig, spool: Option<&TempSpool>) -> AudioChunkQueue {
+        let queue = AudioChunkQueue::new(config, spool.map(|spool| spool.0.clone()));
+        queue.set_enabled(true);
+        queue
+    }
+
+    fn config(
+        capacity: usize,
+        overflow: OverflowPolicy,
+        spool_to_disk: bool,
+    ) -> OutboundQueueConfig {
+        OutboundQueueConfig {
+            capacity,
+            overflow,
+            spool_to_disk,
+            max_spool_mb: 1,
+        }
+    }
+
+    /// 100 KiB of audio tagged with `tag`.
+    fn chunk(tag: u8) -> ProtocolMessage {
+        ProtocolMessage::Audio(vec![tag; 100 * 1024])
+    }
+
+    fn end() -> ProtocolMessage {
+        ProtocolMessage::SessionEnd {
+            session_id: "session".to_string(),
+            cancelled: false,
+        }
+    }
+
+    /// Waits until the spool thread has written everything handed to it.
+    fn settle(queue: &AudioChunkQueue) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            {
+                let _spool = queue.inner.spool.lock().unwrap();
+                let state = queue.inner.state.lock().unwrap();
+                if state.incoming.is_empty() && state.promote == 0 {
+                    return;
+                }
+            }
+            assert!(Instant::now() < deadline, "spool thread is stuck");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    async fn drain(queue: &AudioChunkQueue) -> Vec<ProtocolMessage> {
+        let mut messages = Vec::new();
+        while queue.depth() > 0 {
+            messages.push(queue.pop().await);
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn drops_new_audio_when_full() {
+        let queue = queue(config(3, OverflowPolicy::DropNewest, false), None);
+        for tag in 0..5 {
+            assert_eq!(queue.push(chunk(tag)), tag < 3);
+        }
+        // Control messages are never dropped
+        assert!(queue.push(end()));
+
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(
+            drain(&queue).await,
+            vec![chunk(0), chunk(1), chunk(2), end()]
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_stays_in_memory_without_spooling() {
+        // The app always passes a spool path
+        let spool = TempSpool::new();
+        let queue = queue(config(3, OverflowPolicy::DropOldest, false), Some(&spool));
+        for tag in 0..5 {
+            assert!(queue.push(chunk(tag)));
+        }
+        settle(&queue);
+
+        let status = queue.status();
+        assert_eq!((status.depth, status.spooled, status.dropped), (3, 0, 2));
+        assert!(!spool.0.exists());
+        assert_eq!(drain(&queue).await, vec![chunk(2), chunk(3), chunk(4)]);
+
+        // Nothing is left spilling, so the queue keeps to memory
+        assert!(queue.push(chunk(5)));
+        assert_eq!(queue.status().spooled, 0);
+    }
+
+    #[tokio::test]
+    async fn spooled_messages_come_back_in_order() {
+        let spool = TempSpool::new();
+        let queue = queue(config(2, OverflowPolicy::DropNewest, true), Some(&spool));
+        for tag in 0..6 {
+            assert!(queue.push(chunk(tag)));
+        }
+        assert!(queue.push(end()));
+        settle(&queue);
+
+        let status = queue.status();
+        assert_eq!((status.depth, status.spooled, status.dropped), (7, 5, 0));
+        assert!(spool.len() > 0);
+
+        let mut expected: Vec<_> = (0..6).map(chunk).collect();
+        expected.push(end());
+        assert_eq!(drain(&queue).await, expected);
+        // Read to the end, the file is truncated
+        assert_eq!(spool.len(), 0);
+        assert_eq!(queue.status().spooled, 0);
+    }
+
+    #[tokio::test]
+    async fn spool_keeps_to_its_size_limit() {
+        // 1 MiB holds 10 chunks
+        let spool = TempSpool::new();
+        let queue = queue(config(1, OverflowPolicy::DropNewest, true), Some(&spool));
+        for tag in 0..30 {
+            queue.push(chunk(tag));
+        }
+        settle(&queue);
+        assert_eq!(queue.status().spooled, 10);
+        assert_eq!(queue.dropped(), 19);
+        assert!(spool.len() <= 1024 * 1024);
+        assert_eq!(drain(&queue).await, (0..11).map(chunk).collect::<Vec<_>>());
+
+        // Dropping the oldest keeps the most recent chunks within both limits
+        queue.configure(config(1, OverflowPolicy::DropOldest, true));
+        for tag in 0..30 {
+            assert!(queue.push(chunk(tag)));
+            settle(&queue);
+        }
+        let status = queue.status();
+        assert_eq!((status.depth, status.spooled), (11, 10));
+        assert!(spool.len() <= 2 * 1024 * 1024);
+        assert_eq!(drain(&queue).await, (19..30).map(chunk).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn spool_file_is_compacted_while_read() {
+        let spool = TempSpool::new();
+        let queue = queue(config(1, OverflowPolicy::DropNewest, true), Some(&spool));
+        for tag in 0..5 {
+            queue.push(chunk(tag));
+        }
+
+        // A backlog that is never read to the end
+        for tag in 5..100u8 {
+            queue.push(chunk(tag));
+            settle(&queue);
+            assert_eq!(queue.pop().await, chunk(tag - 5));
+            assert!(spool.len() <= 2 * 1024 * 1024, "{} bytes", spool.len());
+        }
+        assert_eq!(
+            drain(&queue).await,
+            (95..100).map(chunk).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn disabling_throws_away_the_spool() {
+        let spool = TempSpool::new();
+        let queue = queue(config(1, OverflowPolicy::DropNewest, true), Some(&spool));
+        for tag in 0..4 {
+            queue.push(chunk(tag));
+        }
+        settle(&queue);
+        assert!(spool.len() > 0);
+
+        queue.set_enabled(false);
+        assert_eq!(queue.depth(), 0);
+        assert_eq!(spool.len(), 0);
+        assert!(!queue.push(chunk(9)));
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error_handler::ErrorHandler;
+use crate::simple_audio::{get_audio_devices, AudioDevice, SimpleAudioRecorder};
+
+/// How often the input device list is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls the input devices in the background so headsets plugged in or
+/// removed while the app runs show up without a restart.
+pub fn spawn_device_watcher(app: AppHandle<tauri::Wry>) {
+    std::thread::Builder::new()
+        .name("device-watcher".to_string())
+        .spawn(move || {
+            let mut known = get_audio_devices().unwrap_or_default();
+
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+
+                let devices = match get_audio_devices() {
+                    Ok(devices) => devices,
+                    Err(e) => {
+                        eprintln!("Failed to poll audio devices: {}", e);
+                        continue;
+                    }
+                };
+                if devices == known {
+                    continue;
+                }
+
+                println!("Audio devices changed: {} available", devices.len());
+                handle_devices_changed(&app, &devices);
+                known = devices;
+            }
+        })
+        .expect("Failed to spawn device watcher thread");
+}
+
+fn handle_devices_changed(app: &AppHandle<tauri::Wry>, devices: &[AudioDevice]) {
+    let _ = app.emit("audio_devices_changed", devices);
+
+    if let Err(e) = crate::tray::refresh_microphone_menu(app, devices) {
+        eprintln!("Failed to refresh tray microphone menu: {}", e);
+    }
+
+    // Keep capturing if the device in use was unplugged
+    let Some(recorder) = app.try_state::<Arc<SimpleAudioRecorder>>() else {
+        return;
+    };
+    match recorder.recover_lost_device() {
+        Ok(Some((lost, device_name))) => {
+            let message = format!(
+                "Microphone '{}' was disconnected, now using '{}'",
+                lost, device_name
+            );
+            println!("{}", message);
+            let _ = app.emit("audio_error", &message);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            let message = "Microphone was disconnected and no other input device could be opened";
+            let _ = app.emit("audio_error", message);
+            ErrorHandler::new(app.clone()).handle_audio_error(message, Some(&e));
+
+            // Keep what was recorded so far instead of losing it
+            if recorder.is_recording() {
+                if let Err(e) = recorder.stop_recording() {
+                    eprintln!("Failed to stop recording: {}", e);
+                }
+            }
+        }
+    }
+}
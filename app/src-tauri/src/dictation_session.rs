@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::websocket::{SessionComplete, TranscriptionResponse};
+
+/// How long the backend gets to answer `session_end` with `session_complete`,
+/// counted from when `session_end` was sent rather than queued.
+pub const COMPLETE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Emitted on `session_finished` once a dictation session is done, with the
+/// text to insert if there is any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionOutcome {
+    pub session_id: String,
+    pub text: Option<String>,
+    /// The backend never completed the session; `text` is made of the final
+    /// transcripts that arrived before giving up.
+    pub timed_out: bool,
+}
+
+/// Follows the current dictation session until the backend completes it.
+#[derive(Clone, Default)]
+pub struct SessionTracker {
+    pending: Arc<Mutex<Pending>>,
+}
+
+#[derive(Default)]
+struct Pending {
+    session_id: Option<Uuid>,
+    transcripts: Vec<String>,
+    enhanced: Vec<String>,
+    waiter: Option<Sender<Progress>>,
+}
+
+/// What the thread waiting on a session hears about it.
+enum Progress {
+    /// `session_end` reached the backend, so its answer is due.
+    EndSent,
+    /// `session_end` will never reach the backend.
+    Abandoned,
+    Completed(SessionOutcome),
+}
+
+impl Pending {
+    /// Best text from the final transcripts seen so far.
+    fn text_so_far(&self) -> Option<String> {
+        let parts = if self.enhanced.is_empty() {
+            &self.transcripts
+        } else {
+            &self.enhanced
+        };
+        let text = parts.join(" ");
+        (!text.trim().is_empty()).then_some(text)
+    }
+}
+
+impl SessionTracker {
+    pub fn begin(&self, session_id: Uuid) {
+        let previous = std::mem::replace(
+            &mut *self.pending.lock().unwrap(),
+            Pending {
+                session_id: Some(session_id),
+                ..Pending::default()
+            },
+        );
+
+        // A new dictation started before the last one completed
+        if let (Some(waiter), Some(previous_id)) = (&previous.waiter, previous.session_id) {
+            let _ = waiter.send(Progress::Completed(SessionOutcome {
+                session_id: previous_id.to_string(),
+                text: previous.text_so_far(),
+                timed_out: true,
+            }));
+        }
+    }
+
+    pub fn cancel(&self) {
+        *self.pending.lock().unwrap() = Pending::default();
+    }
+
+    /// Keeps final transcripts of the current session in case it times out.
+    pub fn note_transcript(&self, response: &TranscriptionResponse) {
+        let mut pending = self.pending.lock().unwrap();
+        if !response.is_final || pending.session_id.is_none() {
+            return;
+        }
+        match &response.enhanced_text {
+            Some(text) => pending.enhanced.push(text.clone()),
+            None => pending.transcripts.push(response.transcript.clone()),
+        }
+    }
+
+    pub fn complete(&self, result: SessionComplete) {
+        let pending = self.pending.lock().unwrap();
+        let current = pending.session_id.map(|id| id.to_string());
+        match &pending.waiter {
+            Some(waiter) if current.as_deref() == Some(result.session_id.as_str()) => {
+                let text = result.enhanced_text.unwrap_or(result.text);
+                let _ = waiter.send(Progress::Completed(SessionOutcome {
+                    session_id: result.session_id,
+                    text: (!text.trim().is_empty()).then_some(text),
+                    timed_out: false,
+                }));
+            }
+            _ => println!(
+                "Ignoring session_complete for session {}",
+                result.session_id
+            ),
+        }
+    }
+
+    /// Starts the timeout of a finished session once its `session_end` has
+    /// been written to the backend, after any audio queued ahead of it.
+    pub fn end_sent(&self, session_id: &str) {
+        let pending = self.pending.lock().unwrap();
+        let current = pending.session_id.map(|id| id.to_string());
+        if let Some(waiter) = &pending.waiter {
+            if current.as_deref() == Some(session_id) {
+                let _ = waiter.send(Progress::EndSent);
+            }
+        }
+    }
+
+    /// Finishes the session being waited on right away with the transcripts
+    /// received so far, for when the backend will never answer it.
+    pub fn abandon(&self) {
+        if let Some(waiter) = &self.pending.lock().unwrap().waiter {
+            let _ = waiter.send(Progress::Abandoned);
+        }
+    }
+
+    /// Waits, on a thread of its own, for the backend to complete
+    /// `session_id`, then hands the outcome to `on_finished`. Must be called
+    /// before `session_end` is queued. The backend gets `timeout` to answer
+    /// from when `end_sent` reports the `session_end` written, so a session
+    /// ended offline waits for the connection to come back.
+    pub fn finish(
+        &self,
+        session_id: Uuid,
+        timeout: Duration,
+        on_finished: impl FnOnce(SessionOutcome) + Send + 'static,
+    ) {
+        let (waiter, completion) = channel();
+        self.pending.lock().unwrap().waiter = Some(waiter);
+
+        let tracker = self.clone();
+        std::thread::spawn(move || {
+            let mut deadline: Option<Instant> = None;
+            let completed = loop {
+                let progress = match deadline {
+                    None => completion.recv().ok(),
+                    Some(deadline) => completion
+                        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                        .ok(),
+                };
+                match progress {
+                    Some(Progress::EndSent) => {
+                        deadline.get_or_insert_with(|| Instant::now() + timeout);
+                    }
+                    Some(Progress::Completed(outcome)) => break Some(outcome),
+                    Some(Progress::Abandoned) | None => break None,
+                }
+            };
+            let outcome = completed.unwrap_or_else(|| {
+                eprintln!(
+                    "Session {} was not completed, using the transcripts received so far",
+                    session_id
+                );
+                let pending = tracker.pending.lock().unwrap();
+                SessionOutcome {
+                    session_id: session_id.to_string(),
+                    text: pending.text_so_far(),
+                    timed_out: true,
+                }
+            });
+
+            // Leave the tracker alone if a newer session took it over
+            {
+                let mut pending = tracker.pending.lock().unwrap();
+                if pending.session_id == Some(session_id) {
+                    *pending = Pending::default();
+                }
+            }
+
+            on_finished(outcome);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn finish(tracker: &SessionTracker, session_id: Uuid) -> Receiver<SessionOutcome> {
+        let (finished, outcome) = channel();
+        tracker.finish(session_id, TIMEOUT, move |result| {
+            let _ = finished.send(result);
+        });
+        outcome
+    }
+
+    fn final_transcript(text: &str) -> TranscriptionResponse {
+        TranscriptionResponse {
+            transcript: text.to_string(),
+            is_final: true,
+            enhanced_text: None,
+        }
+    }
+
+    #[test]
+    fn session_ended_offline_waits_for_its_replay() {
+        let tracker = SessionTracker::default();
+        let session_id = Uuid::new_v4();
+        tracker.begin(session_id);
+        let outcome = finish(&tracker, session_id);
+
+        // Offline well past the timeout, with session_end still queued
+        assert!(outcome.recv_timeout(TIMEOUT * 3).is_err());
+
+        // The connection is back and the queue replays the session
+        tracker.end_sent(&session_id.to_string());
+        tracker.note_transcript(&final_transcript("hello"));
+        tracker.complete(SessionComplete {
+            session_id: session_id.to_string(),
+            text: "hello world".to_string(),
+            enhanced_text: None,
+        });
+
+        let outcome = outcome.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(outcome.text.as_deref(), Some("hello world"));
+        assert!(!outcome.timed_out);
+    }
+
+    #[test]
+    fn times_out_once_session_end_was_sent() {
+        let tracker = SessionTracker::default();
+        let session_id = Uuid::new_v4();
+        tracker.begin(session_id);
+        tracker.note_transcript(&final_transcript("so far"));
+        let outcome = finish(&tracker, session_id);
+
+        let sent_at = Instant::now();
+        tracker.end_sent(&session_id.to_string());
+        let outcome = outcome.recv_timeout(TIMEOUT * 10).unwrap();
+        assert!(sent_at.elapsed() >= TIMEOUT);
+        assert_eq!(outcome.text.as_deref(), Some("so far"));
+        assert!(outcome.timed_out);
+
+        // Too late for the finished session
+        tracker.complete(SessionComplete {
+            session_id: session_id.to_string(),
+            text: "late".to_string(),
+            enhanced_text: None,
+        });
+    }
+
+    #[test]
+    fn abandoned_session_finishes_at_once() {
+        let tracker = SessionTracker::default();
+        let session_id = Uuid::new_v4();
+        tracker.begin(session_id);
+        let outcome = finish(&tracker, session_id);
+
+        // Another session's end is not this one's
+        tracker.end_sent(&Uuid::new_v4().to_string());
+        assert!(outcome.recv_timeout(TIMEOUT * 2).is_err());
+
+        tracker.abandon();
+        let outcome = outcome.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(outcome.text, None);
+        assert!(outcome.timed_out);
+    }
+}
@@ -0,0 +1,508 @@
+use realfft::num_complex::Complex32;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Optional clean-up applied to captured audio before it is streamed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspConfig {
+    /// Remove DC offset and low-frequency rumble.
+    pub high_pass_enabled: bool,
+    pub high_pass_cutoff_hz: f32,
+    /// Spectral suppression of steady background noise.
+    pub noise_suppression_enabled: bool,
+    /// Maximum attenuation applied to noise (dB).
+    pub noise_suppression_db: f32,
+    /// Automatic gain control towards a target speech level.
+    pub agc_enabled: bool,
+    /// Level the AGC aims for (dBFS).
+    pub agc_target_db: f32,
+    /// Largest boost the AGC will apply (dB).
+    pub agc_max_gain_db: f32,
+}
+
+impl Default for DspConfig {
+    fn default() -> Self {
+        Self {
+            high_pass_enabled: false,
+            high_pass_cutoff_hz: 80.0,
+            noise_suppression_enabled: false,
+            noise_suppression_db: 20.0,
+            agc_enabled: false,
+            agc_target_db: -20.0,
+            agc_max_gain_db: 24.0,
+        }
+    }
+}
+
+impl DspConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.high_pass_enabled || self.noise_suppression_enabled || self.agc_enabled
+    }
+}
+
+/// High-pass, noise suppression and AGC in that order, in one pass over each
+/// block of mono PCM16. Noise suppression delays the output by one FFT frame.
+pub struct DspChain {
+    high_pass: Option<HighPassFilter>,
+    noise_suppressor: Option<NoiseSuppressor>,
+    agc: Option<AutomaticGainControl>,
+    samples: Vec<f32>,
+    suppressed: Vec<f32>,
+}
+
+impl DspChain {
+    pub fn new(config: &DspConfig, sample_rate: u32) -> Self {
+        Self {
+            high_pass: config
+                .high_pass_enabled
+                .then(|| HighPassFilter::new(config.high_pass_cutoff_hz, sample_rate)),
+            noise_suppressor: config
+                .noise_suppression_enabled
+                .then(|| NoiseSuppressor::new(config.noise_suppression_db, sample_rate)),
+            agc: config.agc_enabled.then(|| {
+                AutomaticGainControl::new(config.agc_target_db, config.agc_max_gain_db, sample_rate)
+            }),
+            samples: Vec::new(),
+            suppressed: Vec::new(),
+        }
+    }
+
+    pub fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        self.samples.clear();
+        self.samples
+            .extend(input.iter().map(|&s| s as f32 / i16::MAX as f32));
+
+        if let Some(filter) = self.high_pass.as_mut() {
+            self.samples
+                .iter_mut()
+                .for_each(|sample| *sample = filter.process(*sample));
+        }
+
+        if let Some(suppressor) = self.noise_suppressor.as_mut() {
+            self.suppressed.clear();
+            suppressor.process(&self.samples, &mut self.suppressed);
+            std::mem::swap(&mut self.samples, &mut self.suppressed);
+        }
+
+        self.finish(output);
+    }
+
+    /// Hands out the audio the noise suppressor still holds once the input
+    /// has ended, and primes it for the next session.
+    pub fn flush(&mut self, output: &mut Vec<i16>) {
+        let Some(suppressor) = self.noise_suppressor.as_mut() else {
+            return;
+        };
+        self.samples.clear();
+        suppressor.flush(&mut self.samples);
+        self.finish(output);
+    }
+
+    fn finish(&mut self, output: &mut Vec<i16>) {
+        if let Some(agc) = self.agc.as_mut() {
+            agc.process(&mut self.samples);
+        }
+
+        output.extend(
+            self.samples
+                .iter()
+                .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16),
+        );
+    }
+}
+
+/// Second-order Butterworth high-pass (RBJ biquad).
+struct HighPassFilter {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl HighPassFilter {
+    fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let nyquist = sample_rate as f32 / 2.0;
+        let omega =
+            std::f32::consts::TAU * cutoff_hz.clamp(1.0, nyquist * 0.9) / sample_rate as f32;
+        let alpha = omega.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos = omega.cos();
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 + cos) / 2.0 / a0,
+            b1: -(1.0 + cos) / a0,
+            b2: (1.0 + cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Spectral noise suppression: tracks the noise floor per frequency bin and
+/// attenuates bins that are close to it, using 50% overlapped sqrt-Hann
+/// frames so unprocessed audio reconstructs exactly.
+struct NoiseSuppressor {
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    frame_len: usize,
+    hop: usize,
+    window: Vec<f32>,
+    input: VecDeque<f32>,
+    overlap: Vec<f32>,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    power: Vec<f32>,
+    noise: Vec<f32>,
+    gains: Vec<f32>,
+    min_gain: f32,
+    frames_seen: usize,
+    /// Leading output samples that only carry the priming silence.
+    delay: usize,
+    /// Input samples not yet handed out.
+    pending: usize,
+}
+
+impl NoiseSuppressor {
+    /// Frames averaged to seed the noise estimate.
+    const WARMUP_FRAMES: usize = 10;
+
+    fn new(max_attenuation_db: f32, sample_rate: u32) -> Self {
+        // ~16ms frames: 256 samples at 16 kHz
+        let frame_len = ((sample_rate as usize) / 64).next_power_of_two().max(64);
+        let hop = frame_len / 2;
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(frame_len);
+        let inverse = planner.plan_fft_inverse(frame_len);
+        let spectrum = forward.make_output_vec();
+        let bins = spectrum.len();
+
+        let window = (0..frame_len)
+            .map(|i| {
+                let phase = std::f32::consts::PI * i as f32 / frame_len as f32;
+                phase.sin()
+            })
+            .collect();
+
+        Self {
+            forward,
+            inverse,
+            frame_len,
+            hop,
+            window,
+            input: VecDeque::from(vec![0.0; hop]),
+            overlap: vec![0.0; hop],
+            frame: vec![0.0; frame_len],
+            spectrum,
+            power: vec![0.0; bins],
+            noise: vec![0.0; bins],
+            gains: vec![1.0; bins],
+            min_gain: 10f32.powf(-max_attenuation_db.max(0.0) / 20.0),
+            frames_seen: 0,
+            delay: hop,
+            pending: 0,
+        }
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.pending += input.len();
+        self.input.extend(input.iter());
+        self.run_frames(output);
+    }
+
+    /// Pushes the buffered input out with trailing silence. The noise
+    /// estimate is kept for the next session.
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        while self.pending > 0 {
+            self.input.extend(std::iter::repeat_n(0.0, self.hop));
+            self.run_frames(output);
+        }
+
+        self.input.clear();
+        self.input.extend(std::iter::repeat_n(0.0, self.hop));
+        self.overlap.fill(0.0);
+        self.delay = self.hop;
+    }
+
+    fn run_frames(&mut self, output: &mut Vec<f32>) {
+        while self.input.len() >= self.frame_len {
+            for (i, sample) in self.frame.iter_mut().enumerate() {
+                *sample = self.input[i] * self.window[i];
+            }
+            self.input.drain(..self.hop);
+
+            if self
+                .forward
+                .process(&mut self.frame, &mut self.spectrum)
+                .is_err()
+            {
+                continue;
+            }
+            self.suppress();
+            if self
+                .inverse
+                .process(&mut self.spectrum, &mut self.frame)
+                .is_err()
+            {
+                continue;
+            }
+
+            // Synthesis window and overlap-add; the FFT pair scales by frame_len
+            let scale = 1.0 / self.frame_len as f32;
+            for i in 0..self.hop {
+                let sample = self.overlap[i] + self.frame[i] * self.window[i] * scale;
+                self.overlap[i] = self.frame[i + self.hop] * self.window[i + self.hop] * scale;
+                if self.delay > 0 {
+                    self.delay -= 1;
+                } else if self.pending > 0 {
+                    self.pending -= 1;
+                    output.push(sample);
+                }
+            }
+        }
+    }
+
+    fn suppress(&mut self) {
+        self.frames_seen += 1;
+        let warming_up = self.frames_seen <= Self::WARMUP_FRAMES;
+
+        for (bin, value) in self.spectrum.iter_mut().enumerate() {
+            // Average the bin power over a few frames so random peaks in the
+            // noise do not pass through as if they were speech
+            let power = &mut self.power[bin];
+            *power = 0.6 * *power + 0.4 * value.norm_sqr();
+            let power = *power;
+            let noise = &mut self.noise[bin];
+
+            // Follow the noise floor down quickly and up slowly, so speech
+            // does not get mistaken for noise
+            if warming_up {
+                *noise += (power - *noise) / self.frames_seen as f32;
+            } else if power < *noise {
+                *noise = 0.8 * *noise + 0.2 * power;
+            } else {
+                *noise = 0.995 * *noise + 0.005 * power;
+            }
+
+            // Wiener-style gain with over-subtraction, smoothed over time
+            let target = if power > 0.0 {
+                (1.0 - 2.0 * *noise / power).max(self.min_gain)
+            } else {
+                self.min_gain
+            };
+            let gain = &mut self.gains[bin];
+            *gain = 0.5 * *gain + 0.5 * target;
+            *value *= *gain;
+        }
+    }
+}
+
+/// Slowly steers the level of speech towards a target, boosting no more than
+/// the configured maximum and never amplifying silence.
+struct AutomaticGainControl {
+    target_rms: f32,
+    max_gain: f32,
+    gate_rms: f32,
+    gain: f32,
+    block_len: usize,
+    block_energy: f32,
+    block_fill: usize,
+}
+
+impl AutomaticGainControl {
+    fn new(target_db: f32, max_gain_db: f32, sample_rate: u32) -> Self {
+        Self {
+            target_rms: db_to_amplitude(target_db),
+            max_gain: db_to_amplitude(max_gain_db.max(0.0)),
+            gate_rms: db_to_amplitude(-50.0),
+            gain: 1.0,
+            block_len: (sample_rate as usize / 100).max(1),
+            block_energy: 0.0,
+            block_fill: 0,
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            self.block_energy += *sample * *sample;
+            self.block_fill += 1;
+            if self.block_fill == self.block_len {
+                let rms = (self.block_energy / self.block_len as f32).sqrt();
+                self.block_energy = 0.0;
+                self.block_fill = 0;
+                self.adapt(rms);
+            }
+
+            *sample *= self.gain;
+        }
+    }
+
+    /// Called every 10ms with the level of the input.
+    fn adapt(&mut self, rms: f32) {
+        if rms < self.gate_rms {
+            return;
+        }
+
+        let desired = (self.target_rms / rms).clamp(1.0 / self.max_gain, self.max_gain);
+        // Back off quickly on loud input, raise the gain gradually
+        let rate = if desired < self.gain { 0.3 } else { 0.02 };
+        self.gain += (desired - self.gain) * rate;
+
+        // Keep the boosted block from clipping
+        let peak_headroom = 1.0 / (rms * self.gain * std::f32::consts::SQRT_2).max(1e-6);
+        if peak_headroom < 1.0 {
+            self.gain *= peak_headroom;
+        }
+    }
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn config() -> DspConfig {
+        DspConfig {
+            high_pass_enabled: false,
+            noise_suppression_enabled: false,
+            agc_enabled: false,
+            ..DspConfig::default()
+        }
+    }
+
+    fn tone(frequency: f32, amplitude: f32, len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| {
+                let phase = std::f32::consts::TAU * frequency * i as f32 / RATE as f32;
+                (amplitude * phase.sin() * i16::MAX as f32) as i16
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[i16]) -> f32 {
+        let energy: f32 = samples.iter().map(|&s| (s as f32).powi(2)).sum();
+        (energy / samples.len() as f32).sqrt() / i16::MAX as f32
+    }
+
+    /// Runs `input` through the chain in device-sized blocks and flushes it.
+    fn run(chain: &mut DspChain, input: &[i16]) -> Vec<i16> {
+        let mut output = Vec::new();
+        for block in input.chunks(441) {
+            chain.process(block, &mut output);
+        }
+        chain.flush(&mut output);
+        output
+    }
+
+    #[test]
+    fn high_pass_removes_dc() {
+        let mut chain = DspChain::new(
+            &DspConfig {
+                high_pass_enabled: true,
+                ..config()
+            },
+            RATE,
+        );
+        let input = vec![8000i16; RATE as usize];
+        let output = run(&mut chain, &input);
+
+        let settled = &output[RATE as usize / 2..];
+        let mean = settled.iter().map(|&s| s as f32).sum::<f32>() / settled.len() as f32;
+        assert!(mean.abs() < 1.0, "DC left: {}", mean);
+    }
+
+    #[test]
+    fn agc_gain_stays_within_the_maximum() {
+        let agc = DspConfig {
+            agc_enabled: true,
+            agc_target_db: -20.0,
+            agc_max_gain_db: 12.0,
+            ..config()
+        };
+        let max_gain = db_to_amplitude(12.0);
+
+        // 30 dB below the target: boosted by no more than the maximum
+        let quiet = tone(440.0, 0.01, RATE as usize * 3);
+        let boosted = run(&mut DspChain::new(&agc, RATE), &quiet);
+        let gain = rms(&boosted[RATE as usize * 2..]) / rms(&quiet[RATE as usize * 2..]);
+        assert!(gain > 1.0 && gain <= max_gain * 1.01, "gain {}", gain);
+
+        // 20 dB above the target: cut by no more than the maximum
+        let loud = tone(440.0, 1.0, RATE as usize * 3);
+        let cut = run(&mut DspChain::new(&agc, RATE), &loud);
+        let gain = rms(&cut[RATE as usize * 2..]) / rms(&loud[RATE as usize * 2..]);
+        assert!(gain < 1.0 && gain >= 0.99 / max_gain, "gain {}", gain);
+    }
+
+    #[test]
+    fn silence_stays_silent() {
+        let mut chain = DspChain::new(
+            &DspConfig {
+                high_pass_enabled: true,
+                noise_suppression_enabled: true,
+                agc_enabled: true,
+                ..DspConfig::default()
+            },
+            RATE,
+        );
+        let input = vec![0i16; RATE as usize];
+        let output = run(&mut chain, &input);
+
+        assert_eq!(output.len(), input.len());
+        assert!(output.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn flush_returns_the_tail_held_by_the_noise_suppressor() {
+        // No attenuation, so the overlap-add must reconstruct the input
+        let mut chain = DspChain::new(
+            &DspConfig {
+                noise_suppression_enabled: true,
+                noise_suppression_db: 0.0,
+                ..config()
+            },
+            RATE,
+        );
+
+        for len in [1000, 50] {
+            let input = tone(300.0, 0.5, len);
+            let mut output = Vec::new();
+            chain.process(&input, &mut output);
+            assert!(output.len() < input.len());
+
+            chain.flush(&mut output);
+            assert_eq!(output.len(), input.len());
+            for (&actual, &expected) in output.iter().zip(&input) {
+                assert!((actual - expected).abs() <= 2, "{} != {}", actual, expected);
+            }
+        }
+    }
+}
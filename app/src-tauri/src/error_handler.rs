@@ -90,6 +90,16 @@ impl ErrorHandler {
         self.handle_error(error);
     }
 
+    pub fn handle_audio_error(&self, message: &str, details: Option<&str>) {
+        let mut error = AppError::new(ErrorType::Audio, "Audio Error", message);
+
+        if let Some(details) = details {
+            error = error.with_details(details);
+        }
+
+        self.handle_error(error);
+    }
+
     pub fn handle_clipboard_error(&self, message: &str, details: Option<&str>) {
         let mut error = AppError::new(ErrorType::Clipboard, "Clipboard Error", message);
 
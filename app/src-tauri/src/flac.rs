@@ -0,0 +1,459 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+/// Samples per channel in each FLAC frame.
+const BLOCK_SIZE: usize = 4096;
+const BITS_PER_SAMPLE: u32 = 16;
+const MAX_RICE_PARAMETER: u32 = 14;
+
+/// Writes interleaved PCM16 as a FLAC file. Each block is coded with the best
+/// of the fixed predictors and a single Rice parameter, which keeps the
+/// encoder small and still saves a good part of the size of a WAV file.
+pub fn write_pcm16(
+    path: &Path,
+    samples: &[i16],
+    sample_rate: u32,
+    channels: u16,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create FLAC file: {}", e))?;
+    let mut out = BufWriter::new(file);
+    let channels = channels.clamp(1, 8) as usize;
+    let frames = samples.len() / channels;
+
+    let mut header = BitWriter::new();
+    header.write(u32::from_be_bytes(*b"fLaC"), 32);
+    // Last metadata block, STREAMINFO, 34 bytes
+    header.write(1, 1);
+    header.write(0, 7);
+    header.write(34, 24);
+    header.write(BLOCK_SIZE as u32, 16);
+    header.write(BLOCK_SIZE as u32, 16);
+    header.write(0, 24); // frame sizes unknown
+    header.write(0, 24);
+    header.write(sample_rate, 20);
+    header.write(channels as u32 - 1, 3);
+    header.write(BITS_PER_SAMPLE - 1, 5);
+    header.write((frames as u64 >> 32) as u32, 4);
+    header.write(frames as u32, 32);
+    for _ in 0..4 {
+        header.write(0, 32); // no MD5 signature
+    }
+    out.write_all(&header.finish())
+        .map_err(|e| format!("Failed to write FLAC file: {}", e))?;
+
+    let mut channel = Vec::with_capacity(BLOCK_SIZE);
+    for (frame_number, block) in samples[..frames * channels]
+        .chunks(BLOCK_SIZE * channels)
+        .enumerate()
+    {
+        let block_len = block.len() / channels;
+        let mut frame = BitWriter::new();
+
+        frame.write(0xFFF8, 16); // sync code, fixed block size
+        frame.write(0b0111, 4); // block size in 16 bits after the frame number
+        frame.write(0b0000, 4); // sample rate from STREAMINFO
+        frame.write(channels as u32 - 1, 4); // independent channels
+        frame.write(0b100, 3); // 16 bits per sample
+        frame.write(0, 1);
+        write_utf8_number(&mut frame, frame_number as u64);
+        frame.write(block_len as u32 - 1, 16);
+        let crc = crc8(frame.bytes());
+        frame.write(crc as u32, 8);
+
+        for index in 0..channels {
+            channel.clear();
+            channel.extend(
+                block
+                    .iter()
+                    .skip(index)
+                    .step_by(channels)
+                    .map(|&s| s as i32),
+            );
+            write_subframe(&mut frame, &channel);
+        }
+
+        frame.align();
+        let crc = crc16(frame.bytes());
+        frame.write(crc as u32, 16);
+        out.write_all(&frame.finish())
+            .map_err(|e| format!("Failed to write FLAC file: {}", e))?;
+    }
+
+    out.flush()
+        .map_err(|e| format!("Failed to write FLAC file: {}", e))
+}
+
+/// Length in seconds of a FLAC file, read from its STREAMINFO block.
+pub fn duration_secs(path: &Path) -> Option<f32> {
+    let mut header = [0u8; 42];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if &header[..4] != b"fLaC" || header[4] & 0x7F != 0 {
+        return None;
+    }
+
+    let info = &header[8..];
+    let sample_rate = (info[10] as u32) << 12 | (info[11] as u32) << 4 | (info[12] as u32) >> 4;
+    let total_samples = ((info[13] & 0x0F) as u64) << 32
+        | u32::from_be_bytes([info[14], info[15], info[16], info[17]]) as u64;
+    (sample_rate > 0).then(|| total_samples as f32 / sample_rate as f32)
+}
+
+fn write_subframe(out: &mut BitWriter, samples: &[i32]) {
+    if samples.iter().all(|&s| s == samples[0]) {
+        out.write(0, 1);
+        out.write(0b000000, 6); // constant
+        out.write(0, 1);
+        out.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    // Pick the fixed predictor with the smallest residual
+    let mut best: Option<(usize, Vec<i32>, u32, u64)> = None;
+    for order in 0..=4.min(samples.len() - 1) {
+        let residual = fixed_residual(samples, order);
+        let (parameter, bits) = best_rice_parameter(&residual);
+        if best
+            .as_ref()
+            .is_none_or(|&(_, _, _, best_bits)| bits < best_bits)
+        {
+            best = Some((order, residual, parameter, bits));
+        }
+    }
+
+    let verbatim_bits = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+    match best {
+        Some((order, residual, parameter, bits)) if bits < verbatim_bits => {
+            out.write(0, 1);
+            out.write(0b001000 | order as u32, 6); // fixed predictor
+            out.write(0, 1);
+            for &sample in &samples[..order] {
+                out.write_signed(sample, BITS_PER_SAMPLE);
+            }
+            out.write(0b00, 2); // Rice coding, 4-bit parameter
+            out.write(0, 4); // one partition
+            out.write(parameter, 4);
+            for &value in &residual {
+                out.write_rice(value, parameter);
+            }
+        }
+        _ => {
+            out.write(0, 1);
+            out.write(0b000001, 6); // verbatim
+            out.write(0, 1);
+            for &sample in samples {
+                out.write_signed(sample, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len())
+        .map(|i| {
+            let s = |back: usize| samples[i - back];
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+/// Rice parameter giving the fewest bits for `residual`, and that size.
+fn best_rice_parameter(residual: &[i32]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAMETER)
+        .map(|parameter| {
+            let bits = residual
+                .iter()
+                .map(|&value| (zigzag(value) >> parameter) as u64 + 1 + parameter as u64)
+                .sum();
+            (parameter, bits)
+        })
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((0, u64::MAX))
+}
+
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Frame numbers use the UTF-8 style variable length encoding.
+fn write_utf8_number(out: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        out.write(value as u32, 8);
+        return;
+    }
+
+    let mut continuation = 1;
+    while value >= 1u64 << (5 * continuation + 6) {
+        continuation += 1;
+    }
+    let lead_bits = 6 - continuation;
+    let marker = (0xFF00u32 >> (continuation + 1)) & 0xFF;
+    let lead = (value >> (6 * continuation)) as u32 & ((1 << lead_bits) - 1);
+    out.write(marker | lead, 8);
+    for i in (0..continuation).rev() {
+        out.write(0x80 | ((value >> (6 * i)) & 0x3F) as u32, 8);
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// MSB-first bit packing.
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    pending: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            accumulator: 0,
+            pending: 0,
+        }
+    }
+
+    /// Writes the low `bits` bits of `value`, at most 32.
+    fn write(&mut self, value: u32, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        let mask = if bits == 32 {
+            u32::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        self.accumulator = (self.accumulator << bits) | (value & mask) as u64;
+        self.pending += bits;
+        while self.pending >= 8 {
+            self.pending -= 8;
+            self.bytes.push((self.accumulator >> self.pending) as u8);
+        }
+    }
+
+    fn write_signed(&mut self, value: i32, bits: u32) {
+        self.write(value as u32, bits);
+    }
+
+    fn write_rice(&mut self, value: i32, parameter: u32) {
+        let folded = zigzag(value);
+        let mut quotient = folded >> parameter;
+        while quotient >= 32 {
+            self.write(0, 32);
+            quotient -= 32;
+        }
+        self.write(1, quotient + 1);
+        self.write(folded, parameter);
+    }
+
+    /// Pads with zero bits to the next byte boundary.
+    fn align(&mut self) {
+        if self.pending > 0 {
+            self.write(0, 8 - self.pending);
+        }
+    }
+
+    /// Complete bytes written so far.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    /// Temp file removed when the test ends.
+    struct TempFlac(PathBuf);
+
+    impl TempFlac {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("gorlami-flac-{}.flac", Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempFlac {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Encodes `samples`, decodes them again with claxon and checks that
+    /// nothing changed.
+    fn round_trip(samples: &[i16], sample_rate: u32, channels: u16) -> TempFlac {
+        let file = TempFlac::new();
+        write_pcm16(&file.0, samples, sample_rate, channels).unwrap();
+
+        let mut reader = claxon::FlacReader::open(&file.0).unwrap();
+        let info = reader.streaminfo();
+        assert_eq!(info.sample_rate, sample_rate);
+        assert_eq!(info.channels, channels as u32);
+        assert_eq!(info.bits_per_sample, 16);
+        // A total of 0 means unknown in FLAC
+        assert_eq!(
+            info.samples.unwrap_or(0),
+            (samples.len() / channels as usize) as u64
+        );
+
+        let decoded: Vec<i16> = reader.samples().map(|s| s.unwrap() as i16).collect();
+        assert_eq!(decoded.len(), samples.len());
+        assert!(decoded == samples, "decoded samples differ");
+        file
+    }
+
+    /// Subframe type written for `samples`: 0 constant, 1 verbatim, 8 to 12
+    /// fixed predictor of order 0 to 4.
+    fn subframe_type(samples: &[i32]) -> u8 {
+        let mut out = BitWriter::new();
+        write_subframe(&mut out, samples);
+        out.finish()[0] >> 1 & 0x3F
+    }
+
+    fn sine(len: usize, period: f32, amplitude: f32) -> Vec<i16> {
+        (0..len)
+            .map(|i| ((std::f32::consts::TAU * i as f32 / period).sin() * amplitude) as i16)
+            .collect()
+    }
+
+    /// Full-scale white noise, which no predictor can shrink.
+    fn noise(len: usize) -> Vec<i16> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 16) as i16
+            })
+            .collect()
+    }
+
+    fn widen(samples: &[i16]) -> Vec<i32> {
+        samples.iter().map(|&s| s as i32).collect()
+    }
+
+    #[test]
+    fn constant_blocks() {
+        let samples = vec![-1234i16; BLOCK_SIZE * 2];
+        assert_eq!(subframe_type(&widen(&samples[..BLOCK_SIZE])), 0);
+        round_trip(&samples, 16000, 1);
+        round_trip(&vec![0; BLOCK_SIZE], 16000, 1);
+    }
+
+    #[test]
+    fn verbatim_blocks() {
+        let samples = noise(BLOCK_SIZE * 2);
+        assert_eq!(subframe_type(&widen(&samples[..BLOCK_SIZE])), 1);
+        round_trip(&samples, 16000, 1);
+    }
+
+    #[test]
+    fn predicted_blocks() {
+        let samples = sine(BLOCK_SIZE * 2, 37.0, 12000.0);
+        let kind = subframe_type(&widen(&samples[..BLOCK_SIZE]));
+        assert!((8..=12).contains(&kind), "subframe type {}", kind);
+
+        // Full-scale swings overflow 16 bits in the residual
+        let mut extremes = [i16::MAX, i16::MIN].repeat(BLOCK_SIZE / 2);
+        extremes[7] = 0;
+        round_trip(&[samples, extremes].concat(), 16000, 1);
+    }
+
+    #[test]
+    fn short_final_block() {
+        for tail in [1, 2, 5, 100] {
+            let samples = sine(BLOCK_SIZE * 2 + tail, 50.0, 8000.0);
+            round_trip(&samples, 16000, 1);
+        }
+        round_trip(&sine(3, 50.0, 8000.0), 16000, 1);
+    }
+
+    #[test]
+    fn frame_numbers_past_one_byte() {
+        // 0x90 frames, so the frame number takes two bytes
+        let samples = sine(BLOCK_SIZE * 0x90 + 10, 80.0, 3000.0);
+        round_trip(&samples, 16000, 1);
+
+        for value in [0x7Fu32, 0x80, 0x7FF, 0x800, 0xFFFF, 0x10000, 0x10FFFF] {
+            let mut out = BitWriter::new();
+            write_utf8_number(&mut out, value as u64);
+            let mut expected = [0u8; 4];
+            let expected = char::from_u32(value).unwrap().encode_utf8(&mut expected);
+            assert_eq!(out.finish(), expected.as_bytes(), "{:#x}", value);
+        }
+    }
+
+    #[test]
+    fn multichannel_input() {
+        for channels in [2u16, 3, 6] {
+            let frames = BLOCK_SIZE + 321;
+            let tracks: Vec<Vec<i16>> = (0..channels as usize)
+                .map(|c| match c % 3 {
+                    0 => sine(frames, 20.0 + c as f32, 10000.0),
+                    1 => vec![c as i16 * 100; frames],
+                    _ => noise(frames),
+                })
+                .collect();
+            let mut samples: Vec<i16> = (0..frames)
+                .flat_map(|i| tracks.iter().map(move |track| track[i]))
+                .collect();
+            round_trip(&samples, 48000, channels);
+
+            // A partial frame at the end is left out
+            samples.push(1);
+            let file = TempFlac::new();
+            write_pcm16(&file.0, &samples, 48000, channels).unwrap();
+            let reader = claxon::FlacReader::open(&file.0).unwrap();
+            assert_eq!(reader.streaminfo().samples, Some(frames as u64));
+        }
+    }
+
+    #[test]
+    fn duration_comes_from_streaminfo() {
+        let file = round_trip(&sine(24000 * 2, 40.0, 5000.0), 16000, 2);
+        assert_eq!(duration_secs(&file.0), Some(1.5));
+
+        let file = round_trip(&[], 44100, 1);
+        assert_eq!(duration_secs(&file.0), Some(0.0));
+
+        std::fs::write(&file.0, b"RIFF not a flac file at all, but long enough").unwrap();
+        assert_eq!(duration_secs(&file.0), None);
+    }
+}
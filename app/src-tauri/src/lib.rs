@@ -1,22 +1,39 @@
+mod audio_source;
+mod capture_store;
+mod chunk_queue;
 mod clipboard;
+mod device_watcher;
+mod dictation_session;
+pub mod dsp;
 mod error_handler;
+mod flac;
+mod mic_test;
+mod playback;
+mod recording_archive;
 mod settings;
 mod shortcuts;
 mod simple_audio;
+mod sound_cues;
 mod tray;
+mod vad;
 mod websocket;
 
 use clipboard::{copy_to_clipboard, get_clipboard_text, paste_at_cursor};
+use dictation_session::{SessionOutcome, SessionTracker};
 use error_handler::{clear_error_logs, get_error_logs, report_error, ErrorHandler};
+use playback::{play_last_recording, stop_playback, RecordingPlayer};
+use recording_archive::{delete_recording, list_recordings};
 use settings::{get_app_settings, reset_app_settings, save_app_settings};
 use shortcuts::{
     get_shortcut_config, update_shortcut_config, validate_shortcut, ShortcutManager,
     ShortcutManagerState,
 };
 use simple_audio::{
-    get_audio_data, get_audio_devices, is_recording, select_audio_device, start_recording,
+    get_active_audio_device, get_audio_data, get_audio_devices, get_audio_format, is_recording,
+    pause_recording, resume_recording, run_microphone_test, select_audio_device, start_recording,
     stop_recording, SimpleAudioRecorder,
 };
+use sound_cues::{get_output_devices, SoundCuePlayer};
 use std::sync::{Arc, Mutex};
 use tauri::{Listener, Manager, WebviewUrl, WebviewWindowBuilder};
 use websocket::{
@@ -80,10 +97,20 @@ pub fn run() {
             validate_shortcut,
             get_audio_devices,
             select_audio_device,
+            get_active_audio_device,
             start_recording,
             stop_recording,
+            pause_recording,
+            resume_recording,
             is_recording,
             get_audio_data,
+            get_audio_format,
+            run_microphone_test,
+            get_output_devices,
+            list_recordings,
+            delete_recording,
+            play_last_recording,
+            stop_playback,
             connect_websocket,
             disconnect_websocket,
             get_websocket_status,
@@ -132,8 +159,33 @@ pub fn run() {
                 Arc::new(Mutex::new(shortcut_manager));
             app.manage(shortcut_manager_state);
 
+            // Sound cues, kept out of the captured audio through the gate
+            let sound_cues = SoundCuePlayer::new(saved_settings.sound_cues.clone());
+            let cue_gate = sound_cues.gate();
+            app.manage(sound_cues);
+            app.manage(RecordingPlayer::new(app.handle().clone()));
+
+            // Streamed audio goes from the capture thread to the WebSocket
+            // writer through this queue rather than the event bus, and waits
+            // there while the connection is down
+            let audio_chunks = chunk_queue::AudioChunkQueue::new(
+                saved_settings.websocket.queue.clone(),
+                Some(settings::get_app_data_dir(app.handle()).join("outbound.spool")),
+            );
+
+            // Follows each dictation until the backend completes it
+            let sessions = SessionTracker::default();
+            app.manage(sessions.clone());
+
             // Initialize audio recorder
-            let audio_recorder = Arc::new(SimpleAudioRecorder::new(app.handle().clone()));
+            let audio_recorder = Arc::new(SimpleAudioRecorder::new(
+                app.handle().clone(),
+                audio_source::source_from_env(),
+                Box::new(audio_source::CpalSource::new()),
+                audio_chunks.clone(),
+                cue_gate,
+                sessions,
+            ));
 
             // Set selected microphone if available
             if let Some(ref mic_name) = saved_settings.selected_microphone {
@@ -142,10 +194,21 @@ pub fn run() {
                 }
             }
 
+            // Apply audio settings, opening the warm mic if enabled
+            if let Err(e) = audio_recorder.update_config(saved_settings.audio.clone()) {
+                eprintln!("Failed to apply audio settings: {}", e);
+            }
+
             app.manage(audio_recorder);
 
+            // Pick up microphones that are plugged in or removed later
+            device_watcher::spawn_device_watcher(app.handle().clone());
+
             // Initialize WebSocket client with saved settings
-            let websocket_client = Arc::new(Mutex::new(WebSocketClient::new(app.handle().clone())));
+            let websocket_client = Arc::new(Mutex::new(WebSocketClient::new(
+                app.handle().clone(),
+                audio_chunks,
+            )));
             {
                 let client = websocket_client.lock().unwrap();
                 client.update_config(saved_settings.websocket.clone());
@@ -160,68 +223,48 @@ pub fn run() {
                 }
             });
 
-            // Listen for audio streaming events
-            let app_handle_audio = app.handle().clone();
-            app.listen("send_audio_to_websocket", move |event| {
-                if let Ok(audio_data) = serde_json::from_str::<Vec<u8>>(event.payload()) {
-                    if let Some(ws_client) =
-                        app_handle_audio.try_state::<websocket::WebSocketClientState>()
-                    {
-                        let client = ws_client.lock().unwrap();
-                        if let Err(e) = client.send_audio_data(audio_data) {
-                            let error_handler = ErrorHandler::new(app_handle_audio.clone());
-                            error_handler.handle_websocket_error(
-                                "Failed to send audio data to backend",
-                                Some(&e)
-                            );
-                        }
+            // Play cues for recording events
+            for &(event, cue) in sound_cues::CUE_EVENTS {
+                let app_handle_cue = app.handle().clone();
+                app.listen(event, move |_event| {
+                    if let Some(player) = app_handle_cue.try_state::<SoundCuePlayer>() {
+                        player.play(cue);
                     }
+                });
+            }
+
+            // Playback would be picked up by the microphone
+            let app_handle_playback = app.handle().clone();
+            app.listen("recording_started", move |_event| {
+                if let Some(player) = app_handle_playback.try_state::<RecordingPlayer>() {
+                    player.stop();
                 }
             });
 
-            // Listen for real-time audio chunks
-            let app_handle_chunk = app.handle().clone();
-            app.listen("audio_chunk", move |event| {
-                if let Ok(audio_data) = serde_json::from_str::<Vec<u8>>(event.payload()) {
-                    if let Some(ws_client) =
-                        app_handle_chunk.try_state::<websocket::WebSocketClientState>()
-                    {
-                        let client = ws_client.lock().unwrap();
-                        if let Err(e) = client.send_audio_data(audio_data) {
-                            let error_handler = ErrorHandler::new(app_handle_chunk.clone());
-                            error_handler.handle_websocket_error(
-                                "Failed to stream audio chunk to backend",
-                                Some(&e)
-                            );
-                        }
+            // Keep final transcripts in case the session never completes
+            let app_handle_transcription = app.handle().clone();
+            app.listen("transcription_response", move |event| {
+                if let Ok(response) = serde_json::from_str::<websocket::TranscriptionResponse>(event.payload()) {
+                    if let Some(sessions) = app_handle_transcription.try_state::<SessionTracker>() {
+                        sessions.note_transcript(&response);
                     }
                 }
             });
-            
-            // Listen for WebSocket reconnection events
-            let app_handle_reconnect = app.handle().clone();
-            app.listen("websocket_reconnect", move |_event| {
-                let app_clone = app_handle_reconnect.clone();
-                tokio::spawn(async move {
-                    if let Some(ws_client) = app_clone.try_state::<websocket::WebSocketClientState>() {
-                        if let Err(e) = websocket::reconnect_websocket(&ws_client).await {
-                            let error_handler = ErrorHandler::new(app_clone.clone());
-                            error_handler.handle_websocket_error(
-                                "Automatic reconnection failed",
-                                Some(&e)
-                            );
-                        }
+
+            let app_handle_complete = app.handle().clone();
+            app.listen("session_complete", move |event| {
+                if let Ok(result) = serde_json::from_str::<websocket::SessionComplete>(event.payload()) {
+                    if let Some(sessions) = app_handle_complete.try_state::<SessionTracker>() {
+                        sessions.complete(result);
                     }
-                });
+                }
             });
 
-            // Listen for transcription responses and handle clipboard integration
+            // Paste the text of each finished session
             let app_handle_transcription = app.handle().clone();
-            app.listen("transcription_response", move |event| {
-                if let Ok(response) = serde_json::from_str::<websocket::TranscriptionResponse>(event.payload()) {
-                    // Only handle final transcriptions with enhanced text
-                    if response.is_final {
-                        let text_to_paste = response.enhanced_text.unwrap_or(response.transcript);
+            app.listen("session_finished", move |event| {
+                if let Ok(outcome) = serde_json::from_str::<SessionOutcome>(event.payload()) {
+                    if let Some(text_to_paste) = outcome.text {
                         let error_handler = ErrorHandler::new(app_handle_transcription.clone());
                         
                         // Paste the enhanced text at cursor position
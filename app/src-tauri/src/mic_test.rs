@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio_source::SourceFormat;
+use crate::simple_audio::amplitude_to_db;
+
+/// Overall judgement of a microphone test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MicTestVerdict {
+    Good,
+    Fair,
+    Poor,
+}
+
+/// Result of `run_microphone_test`, measured on the device signal before it
+/// is converted for streaming.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicTestReport {
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_secs: f32,
+    /// Level of the quietest stretches, i.e. the background noise (dBFS).
+    pub noise_floor_db: f32,
+    /// Level of the loudest stretches, normally speech (dBFS).
+    pub speech_level_db: f32,
+    pub peak_db: f32,
+    pub clipping_percent: f32,
+    /// Mean of the signal as a fraction of full scale.
+    pub dc_offset: f32,
+    pub dc_offset_ok: bool,
+    pub verdict: MicTestVerdict,
+    pub suggestions: Vec<String>,
+}
+
+/// Collects level statistics over interleaved samples in the range [-1.0, 1.0].
+pub struct MicTestAnalyzer {
+    format: SourceFormat,
+    window_len: usize,
+    window_energy: f64,
+    window_fill: usize,
+    window_levels: Vec<f32>,
+    samples: u64,
+    clipped: u64,
+    sum: f64,
+    peak: f32,
+}
+
+impl MicTestAnalyzer {
+    /// Level of a sample that counts as clipped.
+    const CLIP_LEVEL: f32 = 0.999;
+    /// DC offsets above this fraction of full scale (-40 dBFS) are reported.
+    const MAX_DC_OFFSET: f32 = 0.01;
+
+    pub fn new(format: SourceFormat) -> Self {
+        // 50ms windows across all channels
+        let window_len = (format.sample_rate as usize * format.channels as usize / 20).max(1);
+        Self {
+            format,
+            window_len,
+            window_energy: 0.0,
+            window_fill: 0,
+            window_levels: Vec::new(),
+            samples: 0,
+            clipped: 0,
+            sum: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.samples += 1;
+            self.sum += sample as f64;
+            self.peak = self.peak.max(sample.abs());
+            if sample.abs() >= Self::CLIP_LEVEL {
+                self.clipped += 1;
+            }
+
+            self.window_energy += (sample * sample) as f64;
+            self.window_fill += 1;
+            if self.window_fill == self.window_len {
+                let rms = (self.window_energy / self.window_len as f64).sqrt() as f32;
+                self.window_levels.push(amplitude_to_db(rms));
+                self.window_energy = 0.0;
+                self.window_fill = 0;
+            }
+        }
+    }
+
+    pub fn report(mut self) -> Result<MicTestReport, String> {
+        if self.window_levels.is_empty() {
+            return Err(format!(
+                "No audio was received from '{}'",
+                self.format.device_name
+            ));
+        }
+
+        self.window_levels.sort_by(|a, b| a.total_cmp(b));
+        let noise_floor_db = percentile(&self.window_levels, 0.1);
+        let speech_level_db = percentile(&self.window_levels, 0.95);
+        let peak_db = amplitude_to_db(self.peak);
+        let clipping_percent = (self.clipped as f64 * 100.0 / self.samples as f64) as f32;
+        let dc_offset = (self.sum / self.samples as f64) as f32;
+        let dc_offset_ok = dc_offset.abs() <= Self::MAX_DC_OFFSET;
+
+        let mut verdict = MicTestVerdict::Good;
+        let mut suggestions = Vec::new();
+        let mut suggest = |level: MicTestVerdict, text: &str| {
+            verdict = verdict.max(level);
+            suggestions.push(text.to_string());
+        };
+
+        if let Some(name) = self.format.fallback_from.as_deref() {
+            suggest(
+                MicTestVerdict::Fair,
+                &format!(
+                    "'{}' is not connected, so the default input device was tested instead.",
+                    name
+                ),
+            );
+        }
+        if peak_db < -60.0 {
+            suggest(
+                MicTestVerdict::Poor,
+                "Almost no sound was picked up. Check that the microphone is not muted and that the right device is selected.",
+            );
+        } else {
+            if clipping_percent > 1.0 {
+                suggest(
+                    MicTestVerdict::Poor,
+                    "The signal clips heavily. Lower the input gain or move further from the microphone.",
+                );
+            } else if clipping_percent > 0.1 {
+                suggest(
+                    MicTestVerdict::Fair,
+                    "The signal clips occasionally. Lower the input gain slightly.",
+                );
+            }
+            if speech_level_db < -35.0 {
+                suggest(
+                    MicTestVerdict::Fair,
+                    "Speech is quiet. Speak closer to the microphone, raise the input gain or enable automatic gain control.",
+                );
+            }
+            if noise_floor_db > -40.0 {
+                suggest(
+                    MicTestVerdict::Poor,
+                    "Background noise is very high. Enable noise suppression or move somewhere quieter.",
+                );
+            } else if noise_floor_db > -50.0 {
+                suggest(
+                    MicTestVerdict::Fair,
+                    "There is noticeable background noise. Noise suppression may help.",
+                );
+            }
+            if speech_level_db - noise_floor_db < 15.0 && speech_level_db >= -35.0 {
+                suggest(
+                    MicTestVerdict::Fair,
+                    "Speech is barely louder than the background. Make sure you spoke during the test.",
+                );
+            }
+        }
+        if !dc_offset_ok {
+            suggest(
+                MicTestVerdict::Fair,
+                "The microphone has a DC offset. Enable low-frequency rumble removal.",
+            );
+        }
+        if self.format.sample_rate < 16000 {
+            suggest(
+                MicTestVerdict::Fair,
+                "The device records below 16 kHz, which limits transcription accuracy.",
+            );
+        }
+
+        let duration_secs = self.samples as f32
+            / (self.format.sample_rate as f32 * self.format.channels.max(1) as f32);
+
+        Ok(MicTestReport {
+            device_name: self.format.device_name,
+            sample_rate: self.format.sample_rate,
+            channels: self.format.channels,
+            duration_secs,
+            noise_floor_db,
+            speech_level_db,
+            peak_db,
+            clipping_percent,
+            dc_offset,
+            dc_offset_ok,
+            verdict,
+            suggestions,
+        })
+    }
+}
+
+/// Value at fraction `p` of sorted `values`.
+fn percentile(values: &[f32], p: f32) -> f32 {
+    let index = ((values.len() - 1) as f32 * p).round() as usize;
+    values[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn format(sample_rate: u32) -> SourceFormat {
+        SourceFormat {
+            device_name: "test".to_string(),
+            sample_rate,
+            channels: 1,
+            fallback_from: None,
+        }
+    }
+
+    /// One second of a 440 Hz tone, clamped to full scale like a device would.
+    fn tone(amplitude: f32) -> Vec<f32> {
+        (0..RATE)
+            .map(|i| {
+                let phase = std::f32::consts::TAU * 440.0 * i as f32 / RATE as f32;
+                (amplitude * phase.sin()).clamp(-1.0, 1.0)
+            })
+            .collect()
+    }
+
+    /// One second of deterministic white noise.
+    fn noise(amplitude: f32) -> Vec<f32> {
+        let mut state = 0x1234_5678u32;
+        (0..RATE)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                amplitude * ((state >> 8) as f32 / (1 << 23) as f32 - 1.0)
+            })
+            .collect()
+    }
+
+    fn mixed(a: &[f32], b: &[f32]) -> Vec<f32> {
+        a.iter().zip(b).map(|(a, b)| a + b).collect()
+    }
+
+    fn analyze(sample_rate: u32, signal: &[f32]) -> MicTestReport {
+        let mut analyzer = MicTestAnalyzer::new(format(sample_rate));
+        for block in signal.chunks(441) {
+            analyzer.push(block);
+        }
+        analyzer.report().unwrap()
+    }
+
+    #[test]
+    fn verdicts_for_synthetic_signals() {
+        let quiet_room = noise(0.0003);
+        let cases = vec![
+            (
+                "speech over a quiet room",
+                RATE,
+                [quiet_room.clone(), tone(0.3)].concat(),
+                MicTestVerdict::Good,
+                None,
+            ),
+            (
+                "silence",
+                RATE,
+                vec![0.0; RATE as usize * 2],
+                MicTestVerdict::Poor,
+                Some("Almost no sound"),
+            ),
+            (
+                "clipped tone",
+                RATE,
+                [quiet_room.clone(), tone(2.0)].concat(),
+                MicTestVerdict::Poor,
+                Some("clips heavily"),
+            ),
+            (
+                "noisy tone",
+                RATE,
+                [noise(0.1), mixed(&tone(0.3), &noise(0.1))].concat(),
+                MicTestVerdict::Poor,
+                Some("Background noise is very high"),
+            ),
+            (
+                "tone barely above moderate noise",
+                RATE,
+                [noise(0.01), mixed(&tone(0.035), &noise(0.01))].concat(),
+                MicTestVerdict::Fair,
+                Some("barely louder than the background"),
+            ),
+            (
+                "quiet speech",
+                RATE,
+                [quiet_room.clone(), tone(0.01)].concat(),
+                MicTestVerdict::Fair,
+                Some("Speech is quiet"),
+            ),
+            (
+                "DC offset",
+                RATE,
+                [quiet_room.clone(), tone(0.3)]
+                    .concat()
+                    .iter()
+                    .map(|s| s + 0.05)
+                    .collect(),
+                // The offset also lifts the measured noise floor
+                MicTestVerdict::Poor,
+                Some("DC offset"),
+            ),
+            (
+                "narrowband device",
+                8000,
+                [quiet_room.clone(), tone(0.3)].concat(),
+                MicTestVerdict::Fair,
+                Some("below 16 kHz"),
+            ),
+        ];
+
+        for (name, sample_rate, signal, verdict, suggestion) in cases {
+            let report = analyze(sample_rate, &signal);
+            assert_eq!(report.verdict, verdict, "{}: {:?}", name, report);
+            match suggestion {
+                Some(text) => assert!(
+                    report.suggestions.iter().any(|s| s.contains(text)),
+                    "{}: {:?}",
+                    name,
+                    report.suggestions
+                ),
+                None => assert!(report.suggestions.is_empty(), "{}: {:?}", name, report),
+            }
+        }
+    }
+
+    #[test]
+    fn measures_levels_and_clipping() {
+        let report = analyze(RATE, &[noise(0.0003), tone(2.0)].concat());
+        assert!(report.noise_floor_db < -70.0, "{:?}", report);
+        assert!(report.speech_level_db > -3.0, "{:?}", report);
+        assert!(report.peak_db > -0.1);
+        // Two thirds of the clamped tone sits at full scale
+        assert!(
+            (30.0..36.0).contains(&report.clipping_percent),
+            "{:?}",
+            report
+        );
+        assert!((report.duration_secs - 2.0).abs() < 1e-3);
+
+        let report = analyze(RATE, &vec![0.0; RATE as usize]);
+        assert_eq!(report.peak_db, -100.0);
+        assert_eq!(report.clipping_percent, 0.0);
+        assert!(report.dc_offset_ok);
+    }
+
+    #[test]
+    fn no_audio_is_an_error() {
+        assert!(MicTestAnalyzer::new(format(RATE)).report().is_err());
+    }
+}
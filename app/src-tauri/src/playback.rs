@@ -0,0 +1,306 @@
+use cpal::traits::{DeviceTrait, StreamTrait};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::simple_audio::{AudioFormat, SimpleAudioRecorder};
+use crate::sound_cues::{self, SoundCuePlayer};
+
+/// How often `playback_position` is emitted while playing.
+const POSITION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Emitted on `playback_position` while a recording plays, and once more
+/// with `playing: false` when it ends or is stopped.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackPosition {
+    pub position_secs: f32,
+    pub duration_secs: f32,
+    pub playing: bool,
+}
+
+enum PlaybackCommand {
+    Play {
+        samples: Arc<Vec<i16>>,
+        format: AudioFormat,
+        start_secs: f32,
+        device: Option<String>,
+        reply: Sender<Result<(), String>>,
+    },
+    Stop,
+}
+
+/// Plays recordings back on an output device. Like the sound cues, the cpal
+/// stream lives on a thread of its own.
+pub struct RecordingPlayer {
+    commands: Sender<PlaybackCommand>,
+}
+
+impl RecordingPlayer {
+    pub fn new(app: AppHandle<tauri::Wry>) -> Self {
+        let (commands, receiver) = channel();
+        std::thread::Builder::new()
+            .name("playback".to_string())
+            .spawn(move || run_player(app, receiver))
+            .expect("Failed to spawn playback thread");
+
+        Self { commands }
+    }
+
+    /// Plays interleaved PCM16 from `start_secs`, replacing anything that is
+    /// already playing.
+    pub fn play(
+        &self,
+        samples: Vec<i16>,
+        format: AudioFormat,
+        start_secs: f32,
+        device: Option<String>,
+    ) -> Result<(), String> {
+        if samples.is_empty() {
+            return Err("There is no recording to play".to_string());
+        }
+
+        let (reply, response) = channel();
+        self.commands
+            .send(PlaybackCommand::Play {
+                samples: Arc::new(samples),
+                format,
+                start_secs,
+                device,
+                reply,
+            })
+            .map_err(|_| "Playback thread is not running".to_string())?;
+        response
+            .recv()
+            .map_err(|_| "Playback thread is not running".to_string())?
+    }
+
+    pub fn stop(&self) {
+        let _ = self.commands.send(PlaybackCommand::Stop);
+    }
+}
+
+/// A recording being played and how far it has got.
+struct Playback {
+    _stream: cpal::Stream,
+    /// Sample frames of the recording played so far.
+    position: Arc<AtomicU64>,
+    frames: u64,
+    sample_rate: u32,
+}
+
+impl Playback {
+    fn is_done(&self) -> bool {
+        self.position.load(Ordering::SeqCst) >= self.frames
+    }
+
+    fn report(&self, app: &AppHandle<tauri::Wry>, playing: bool) {
+        let position = self.position.load(Ordering::SeqCst).min(self.frames);
+        let _ = app.emit(
+            "playback_position",
+            PlaybackPosition {
+                position_secs: position as f32 / self.sample_rate as f32,
+                duration_secs: self.frames as f32 / self.sample_rate as f32,
+                playing,
+            },
+        );
+    }
+}
+
+fn run_player(app: AppHandle<tauri::Wry>, commands: Receiver<PlaybackCommand>) {
+    let mut current: Option<Playback> = None;
+
+    loop {
+        // Wake up regularly to report the position while playing
+        let command = if current.is_some() {
+            match commands.recv_timeout(POSITION_INTERVAL) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+
+        match command {
+            Some(PlaybackCommand::Play {
+                samples,
+                format,
+                start_secs,
+                device,
+                reply,
+            }) => {
+                if let Some(playback) = current.take() {
+                    playback.report(&app, false);
+                }
+                match open_playback(samples, &format, start_secs, device.as_deref()) {
+                    Ok(playback) => {
+                        current = Some(playback);
+                        let _ = reply.send(Ok(()));
+                    }
+                    Err(e) => {
+                        let _ = reply.send(Err(e));
+                    }
+                }
+            }
+            Some(PlaybackCommand::Stop) => {
+                if let Some(playback) = current.take() {
+                    playback.report(&app, false);
+                }
+            }
+            None => {}
+        }
+
+        if let Some(playback) = &current {
+            if playback.is_done() {
+                playback.report(&app, false);
+                current = None;
+            } else {
+                playback.report(&app, true);
+            }
+        }
+    }
+}
+
+fn open_playback(
+    samples: Arc<Vec<i16>>,
+    format: &AudioFormat,
+    start_secs: f32,
+    device: Option<&str>,
+) -> Result<Playback, String> {
+    let device = sound_cues::output_device(device)?;
+    let output_config = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get default output config: {}", e))?;
+    let sample_format = output_config.sample_format();
+    let stream_config: cpal::StreamConfig = output_config.into();
+
+    let channels = format.channels.max(1) as usize;
+    let frames = (samples.len() / channels) as u64;
+    let start = ((start_secs.max(0.0) * format.sample_rate as f32) as u64).min(frames);
+    let position = Arc::new(AtomicU64::new(start));
+    let source = PlaybackSource {
+        samples,
+        channels,
+        output_channels: stream_config.channels as usize,
+        step: format.sample_rate as f64 / stream_config.sample_rate.0 as f64,
+        cursor: start as f64,
+        position: position.clone(),
+    };
+
+    let stream = match sample_format {
+        cpal::SampleFormat::I8 => build_playback_stream::<i8>(&device, &stream_config, source),
+        cpal::SampleFormat::I16 => build_playback_stream::<i16>(&device, &stream_config, source),
+        cpal::SampleFormat::I32 => build_playback_stream::<i32>(&device, &stream_config, source),
+        cpal::SampleFormat::U8 => build_playback_stream::<u8>(&device, &stream_config, source),
+        cpal::SampleFormat::U16 => build_playback_stream::<u16>(&device, &stream_config, source),
+        cpal::SampleFormat::U32 => build_playback_stream::<u32>(&device, &stream_config, source),
+        cpal::SampleFormat::F32 => build_playback_stream::<f32>(&device, &stream_config, source),
+        cpal::SampleFormat::F64 => build_playback_stream::<f64>(&device, &stream_config, source),
+        other => return Err(format!("Unsupported sample format: {}", other)),
+    }
+    .map_err(|e| format!("Failed to build output stream: {}", e))?;
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start playback: {}", e))?;
+
+    Ok(Playback {
+        _stream: stream,
+        position,
+        frames,
+        sample_rate: format.sample_rate,
+    })
+}
+
+/// Resamples the recording to the output device rate with linear
+/// interpolation, repeating its channels across the device's.
+struct PlaybackSource {
+    samples: Arc<Vec<i16>>,
+    channels: usize,
+    output_channels: usize,
+    /// Recording frames per output frame.
+    step: f64,
+    cursor: f64,
+    position: Arc<AtomicU64>,
+}
+
+impl PlaybackSource {
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        self.samples
+            .get(frame * self.channels + channel)
+            .map_or(0.0, |&s| s as f32 / 32768.0)
+    }
+
+    fn fill<T: cpal::Sample + cpal::FromSample<f32>>(&mut self, data: &mut [T]) {
+        for frame in data.chunks_mut(self.output_channels.max(1)) {
+            let index = self.cursor as usize;
+            let fraction = (self.cursor - index as f64) as f32;
+            for (channel, out) in frame.iter_mut().enumerate() {
+                let channel = channel % self.channels;
+                let a = self.sample(index, channel);
+                let b = self.sample(index + 1, channel);
+                *out = T::from_sample(a + (b - a) * fraction);
+            }
+            self.cursor += self.step;
+        }
+        self.position.store(self.cursor as u64, Ordering::SeqCst);
+    }
+}
+
+fn build_playback_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut source: PlaybackSource,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| source.fill(data),
+        |err| eprintln!("Playback output error: {}", err),
+        None,
+    )
+}
+
+/// Plays the last finished recording on the sound cue output device. Shared
+/// by the command and the tray menu.
+pub fn play_last_recording_on<R: Runtime>(
+    app: &AppHandle<R>,
+    start_secs: f32,
+) -> Result<(), String> {
+    let recorder = app
+        .try_state::<Arc<SimpleAudioRecorder>>()
+        .ok_or_else(|| "Audio recorder not initialized".to_string())?;
+    if recorder.is_recording() {
+        return Err("Stop recording before playing back".to_string());
+    }
+    let player = app
+        .try_state::<RecordingPlayer>()
+        .ok_or_else(|| "Playback not initialized".to_string())?;
+    let device = app
+        .try_state::<SoundCuePlayer>()
+        .and_then(|cues| cues.output_device_name());
+
+    player.play(
+        recorder.get_audio_data(),
+        recorder.output_format(),
+        start_secs,
+        device,
+    )
+}
+
+#[tauri::command]
+pub fn play_last_recording(app: AppHandle, start_secs: Option<f32>) -> Result<(), String> {
+    play_last_recording_on(&app, start_secs.unwrap_or(0.0))
+}
+
+#[tauri::command]
+pub fn stop_playback(state: tauri::State<RecordingPlayer>) {
+    state.stop();
+}
@@ -0,0 +1,360 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::simple_audio::AudioFormat;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    #[default]
+    Wav,
+    Flac,
+}
+
+impl ArchiveFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Wav => "wav",
+            ArchiveFormat::Flac => "flac",
+        }
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "wav" => Some(ArchiveFormat::Wav),
+            "flac" => Some(ArchiveFormat::Flac),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps a copy of the audio of every session on disk, so a wrong transcript
+/// can be checked against what was actually sent. Limits of 0 are unlimited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    pub format: ArchiveFormat,
+    /// Most recordings kept; the oldest are deleted first.
+    pub max_recordings: usize,
+    pub max_age_days: u64,
+    /// Total size of the archive (megabytes).
+    pub max_total_mb: u64,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: ArchiveFormat::Wav,
+            max_recordings: 50,
+            max_age_days: 30,
+            max_total_mb: 500,
+        }
+    }
+}
+
+/// An archived session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub session_id: String,
+    pub format: ArchiveFormat,
+    pub path: String,
+    pub size_bytes: u64,
+    /// Unix time the recording was written, in seconds.
+    pub created_at: u64,
+    pub duration_secs: Option<f32>,
+}
+
+pub fn recordings_dir(app: &AppHandle) -> PathBuf {
+    crate::settings::get_app_data_dir(app).join("recordings")
+}
+
+/// Writes a finished session to the archive on a background thread, then
+/// removes recordings beyond the retention limits.
+pub fn archive_session(
+    app: &AppHandle,
+    config: ArchiveConfig,
+    session_id: Uuid,
+    samples: Vec<i16>,
+    format: AudioFormat,
+) {
+    let dir = recordings_dir(app);
+    std::thread::spawn(move || {
+        match write_recording(&dir, &config, session_id, &samples, &format) {
+            Ok(path) => println!("Archived recording to {}", path.display()),
+            Err(e) => eprintln!("Failed to archive recording: {}", e),
+        }
+        apply_retention(&dir, &config);
+    });
+}
+
+fn write_recording(
+    dir: &Path,
+    config: &ArchiveConfig,
+    session_id: Uuid,
+    samples: &[i16],
+    format: &AudioFormat,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create recordings folder: {}", e))?;
+
+    // Written under a temporary name so a half-written file is never listed
+    let path = dir.join(format!("{}.{}", session_id, config.format.extension()));
+    let partial = path.with_extension("part");
+    match config.format {
+        ArchiveFormat::Wav => {
+            let spec = hound::WavSpec {
+                channels: format.channels,
+                sample_rate: format.sample_rate,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut writer = hound::WavWriter::create(&partial, spec)
+                .map_err(|e| format!("Failed to create WAV file: {}", e))?;
+            for &sample in samples {
+                writer
+                    .write_sample(sample)
+                    .map_err(|e| format!("Failed to write WAV file: {}", e))?;
+            }
+            writer
+                .finalize()
+                .map_err(|e| format!("Failed to write WAV file: {}", e))?;
+        }
+        ArchiveFormat::Flac => {
+            crate::flac::write_pcm16(&partial, samples, format.sample_rate, format.channels)?;
+        }
+    }
+
+    fs::rename(&partial, &path).map_err(|e| format!("Failed to save recording: {}", e))?;
+    Ok(path)
+}
+
+/// Archived recordings, newest first.
+fn list(dir: &Path) -> Vec<RecordingInfo> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut recordings: Vec<RecordingInfo> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let format = ArchiveFormat::from_extension(path.extension()?.to_str()?)?;
+            let session_id = Uuid::parse_str(path.file_stem()?.to_str()?).ok()?;
+            let metadata = fs::metadata(&path).ok()?;
+            let created_at = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|age| age.as_secs())
+                .unwrap_or(0);
+            let duration_secs = match format {
+                ArchiveFormat::Wav => hound::WavReader::open(&path)
+                    .ok()
+                    .map(|reader| reader.duration() as f32 / reader.spec().sample_rate as f32),
+                ArchiveFormat::Flac => crate::flac::duration_secs(&path),
+            };
+
+            Some(RecordingInfo {
+                session_id: session_id.to_string(),
+                format,
+                path: path.to_string_lossy().to_string(),
+                size_bytes: metadata.len(),
+                created_at,
+                duration_secs,
+            })
+        })
+        .collect();
+
+    recordings.sort_by_key(|recording| Reverse(recording.created_at));
+    recordings
+}
+
+/// Deletes the oldest recordings until the archive is within every limit.
+fn apply_retention(dir: &Path, config: &ArchiveConfig) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let max_age_secs = config.max_age_days * 24 * 60 * 60;
+    let max_total_bytes = config.max_total_mb * 1024 * 1024;
+
+    let mut total_bytes = 0;
+    for (index, recording) in list(dir).into_iter().enumerate() {
+        total_bytes += recording.size_bytes;
+        let too_many = config.max_recordings > 0 && index >= config.max_recordings;
+        let too_old = max_age_secs > 0 && now.saturating_sub(recording.created_at) > max_age_secs;
+        let too_large = max_total_bytes > 0 && total_bytes > max_total_bytes;
+
+        if too_many || too_old || too_large {
+            match fs::remove_file(&recording.path) {
+                Ok(()) => println!("Removed archived recording {}", recording.session_id),
+                Err(e) => eprintln!("Failed to remove archived recording: {}", e),
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_recordings(app: AppHandle) -> Result<Vec<RecordingInfo>, String> {
+    Ok(list(&recordings_dir(&app)))
+}
+
+#[tauri::command]
+pub fn delete_recording(app: AppHandle, session_id: String) -> Result<(), String> {
+    // Only ever touch files named after a session
+    let session_id =
+        Uuid::parse_str(&session_id).map_err(|_| format!("Invalid session ID: {}", session_id))?;
+
+    delete(&recordings_dir(&app), session_id)
+}
+
+fn delete(dir: &Path, session_id: Uuid) -> Result<(), String> {
+    let mut deleted = false;
+    for format in [ArchiveFormat::Wav, ArchiveFormat::Flac] {
+        let path = dir.join(format!("{}.{}", session_id, format.extension()));
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete recording: {}", e))?;
+            deleted = true;
+        }
+    }
+
+    if deleted {
+        Ok(())
+    } else {
+        Err(format!("No recording found for session {}", session_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    struct TempArchive(PathBuf);
+
+    impl TempArchive {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("gorlami-archive-{}", Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        /// Adds a recording of `size` bytes last written `age` ago.
+        fn add(&self, age: Duration, size: usize) -> Uuid {
+            let session_id = Uuid::new_v4();
+            self.add_file(&format!("{}.wav", session_id), age, size);
+            session_id
+        }
+
+        fn add_file(&self, name: &str, age: Duration, size: usize) {
+            let path = self.0.join(name);
+            fs::write(&path, vec![0u8; size]).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        }
+
+        fn session_ids(&self) -> Vec<Uuid> {
+            list(&self.0)
+                .iter()
+                .map(|recording| Uuid::parse_str(&recording.session_id).unwrap())
+                .collect()
+        }
+    }
+
+    impl Drop for TempArchive {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn limits(max_recordings: usize, max_age_days: u64, max_total_mb: u64) -> ArchiveConfig {
+        ArchiveConfig {
+            enabled: true,
+            format: ArchiveFormat::Wav,
+            max_recordings,
+            max_age_days,
+            max_total_mb,
+        }
+    }
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 60 * 60)
+    }
+
+    #[test]
+    fn keeps_the_newest_recordings_up_to_the_count() {
+        let archive = TempArchive::new();
+        let newest_first: Vec<Uuid> = (0..5).map(|age| archive.add(hours(age), 16)).collect();
+
+        apply_retention(&archive.0, &limits(3, 0, 0));
+        assert_eq!(archive.session_ids(), newest_first[..3]);
+    }
+
+    #[test]
+    fn deletes_recordings_older_than_the_age_limit() {
+        let archive = TempArchive::new();
+        let recent = archive.add(hours(24), 16);
+        let month_old = archive.add(hours(29 * 24), 16);
+        archive.add(hours(31 * 24), 16);
+
+        apply_retention(&archive.0, &limits(0, 30, 0));
+        assert_eq!(archive.session_ids(), [recent, month_old]);
+    }
+
+    #[test]
+    fn deletes_the_oldest_recordings_beyond_the_total_size() {
+        let archive = TempArchive::new();
+        let newest_first: Vec<Uuid> = (0..4)
+            .map(|age| archive.add(hours(age), 400 * 1024))
+            .collect();
+
+        apply_retention(&archive.0, &limits(0, 0, 1));
+        assert_eq!(archive.session_ids(), newest_first[..2]);
+    }
+
+    #[test]
+    fn limits_of_zero_keep_everything() {
+        let archive = TempArchive::new();
+        for age in 0..5 {
+            archive.add(hours(age * 24 * 365), 400 * 1024);
+        }
+
+        apply_retention(&archive.0, &limits(0, 0, 0));
+        assert_eq!(archive.session_ids().len(), 5);
+    }
+
+    #[test]
+    fn lists_only_finished_recordings_named_after_a_session() {
+        let archive = TempArchive::new();
+        let session_id = archive.add(hours(0), 16);
+        archive.add_file(&format!("{}.part", Uuid::new_v4()), hours(0), 16);
+        archive.add_file("notes.wav", hours(0), 16);
+        archive.add_file(&format!("{}.mp3", Uuid::new_v4()), hours(0), 16);
+
+        assert_eq!(archive.session_ids(), [session_id]);
+
+        // Retention never touches what it does not list
+        apply_retention(&archive.0, &limits(1, 0, 0));
+        assert_eq!(fs::read_dir(&archive.0).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn deletes_a_recording_by_session() {
+        let archive = TempArchive::new();
+        let kept = archive.add(hours(0), 16);
+        let deleted = archive.add(hours(1), 16);
+
+        delete(&archive.0, deleted).unwrap();
+        assert_eq!(archive.session_ids(), [kept]);
+        assert!(delete(&archive.0, deleted).is_err());
+    }
+}
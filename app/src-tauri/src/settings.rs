@@ -1,4 +1,6 @@
 use crate::shortcuts::ShortcutConfig;
+use crate::simple_audio::AudioConfig;
+use crate::sound_cues::{SoundCueConfig, SoundCuePlayer};
 use crate::websocket::WebSocketConfig;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -10,6 +12,10 @@ pub struct AppSettings {
     pub shortcuts: ShortcutConfig,
     pub websocket: WebSocketConfig,
     pub selected_microphone: Option<String>,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub sound_cues: SoundCueConfig,
 }
 
 impl Default for AppSettings {
@@ -18,6 +24,8 @@ impl Default for AppSettings {
             shortcuts: ShortcutConfig::default(),
             websocket: WebSocketConfig::default(),
             selected_microphone: None,
+            audio: AudioConfig::default(),
+            sound_cues: SoundCueConfig::default(),
         }
     }
 }
@@ -36,11 +44,13 @@ impl std::fmt::Debug for AppSettings {
                 ),
             )
             .field("selected_microphone", &self.selected_microphone)
+            .field("audio", &self.audio)
+            .field("sound_cues", &self.sound_cues)
             .finish()
     }
 }
 
-pub fn get_settings_path(app: &AppHandle) -> PathBuf {
+pub fn get_app_data_dir(app: &AppHandle) -> PathBuf {
     let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| {
         dirs::home_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -52,7 +62,11 @@ pub fn get_settings_path(app: &AppHandle) -> PathBuf {
         let _ = fs::create_dir_all(&app_data_dir);
     }
 
-    app_data_dir.join("settings.json")
+    app_data_dir
+}
+
+pub fn get_settings_path(app: &AppHandle) -> PathBuf {
+    get_app_data_dir(app).join("settings.json")
 }
 
 pub fn load_settings(app: &AppHandle) -> AppSettings {
@@ -93,6 +107,7 @@ pub fn save_app_settings(
     shortcut_state: tauri::State<crate::shortcuts::ShortcutManagerState>,
     websocket_state: tauri::State<crate::websocket::WebSocketClientState>,
     audio_state: tauri::State<std::sync::Arc<crate::simple_audio::SimpleAudioRecorder>>,
+    cue_state: tauri::State<SoundCuePlayer>,
 ) -> Result<(), String> {
     // Save settings to file
     save_settings(&app, &settings)?;
@@ -117,6 +132,10 @@ pub fn save_app_settings(
             eprintln!("Failed to select microphone '{}': {}", mic_name, e);
         }
     }
+    if let Err(e) = audio_state.inner().update_config(settings.audio.clone()) {
+        eprintln!("Failed to apply audio settings: {}", e);
+    }
+    cue_state.update_config(settings.sound_cues.clone());
 
     println!("Settings saved and applied successfully");
     Ok(())
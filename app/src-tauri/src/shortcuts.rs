@@ -1,20 +1,180 @@
 use crate::simple_audio::SimpleAudioRecorder;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager, Runtime, State};
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
+/// How a shortcut drives recording.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutMode {
+    /// Press once to start, press again to stop.
+    #[default]
+    #[serde(alias = "Toggle")]
+    Toggle,
+    /// Active while the shortcut is held down.
+    #[serde(alias = "HoldToTalk")]
+    HoldToTalk,
+}
+
+/// A global shortcut and how it reacts to being pressed and released.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutBinding {
+    pub keys: String,
+    #[serde(default)]
+    pub mode: ShortcutMode,
+}
+
+impl ShortcutBinding {
+    fn toggle(keys: &str) -> Self {
+        Self {
+            keys: keys.to_string(),
+            mode: ShortcutMode::Toggle,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredShortcutConfig")]
 pub struct ShortcutConfig {
-    pub transcription: String,
-    pub edit: String,
+    pub transcription: ShortcutBinding,
+    pub edit: ShortcutBinding,
+    /// Pauses and resumes the current recording; empty keys to disable.
+    pub pause: ShortcutBinding,
+    /// In hold-to-talk mode, presses shorter than this are ignored (milliseconds).
+    pub hold_threshold_ms: u64,
+}
+
+fn default_hold_threshold_ms() -> u64 {
+    300
+}
+
+fn default_pause_shortcut() -> ShortcutBinding {
+    ShortcutBinding::toggle("CommandOrControl+Ctrl+P")
 }
 
 impl Default for ShortcutConfig {
     fn default() -> Self {
         Self {
-            transcription: "CommandOrControl+Ctrl+Space".to_string(),
-            edit: "CommandOrControl+Ctrl+E".to_string(),
+            transcription: ShortcutBinding::toggle("CommandOrControl+Ctrl+Space"),
+            edit: ShortcutBinding::toggle("CommandOrControl+Ctrl+E"),
+            pause: default_pause_shortcut(),
+            hold_threshold_ms: default_hold_threshold_ms(),
+        }
+    }
+}
+
+/// `ShortcutConfig` as stored, including settings saved before bindings had
+/// a mode of their own: plain key strings and one `transcription_mode`.
+#[derive(Deserialize)]
+struct StoredShortcutConfig {
+    transcription: StoredBinding,
+    edit: StoredBinding,
+    #[serde(default)]
+    pause: Option<StoredBinding>,
+    #[serde(default)]
+    transcription_mode: ShortcutMode,
+    #[serde(default = "default_hold_threshold_ms")]
+    hold_threshold_ms: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredBinding {
+    Binding(ShortcutBinding),
+    Keys(String),
+}
+
+impl StoredBinding {
+    fn with_default_mode(self, mode: ShortcutMode) -> ShortcutBinding {
+        match self {
+            StoredBinding::Binding(binding) => binding,
+            StoredBinding::Keys(keys) => ShortcutBinding { keys, mode },
+        }
+    }
+}
+
+impl From<StoredShortcutConfig> for ShortcutConfig {
+    fn from(stored: StoredShortcutConfig) -> Self {
+        Self {
+            transcription: stored
+                .transcription
+                .with_default_mode(stored.transcription_mode),
+            edit: stored.edit.with_default_mode(ShortcutMode::Toggle),
+            pause: stored.pause.map_or_else(default_pause_shortcut, |pause| {
+                pause.with_default_mode(ShortcutMode::Toggle)
+            }),
+            hold_threshold_ms: stored.hold_threshold_ms,
+        }
+    }
+}
+
+/// What a press or release of a binding asks for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BindingAction {
+    /// Toggle mode: flip between on and off.
+    Toggle,
+    /// Hold-to-talk: the shortcut went down.
+    Start,
+    /// Hold-to-talk: the shortcut that started something was released
+    /// after a hold.
+    Stop,
+    /// Hold-to-talk: the shortcut that started something was released
+    /// before the hold threshold.
+    Tap,
+}
+
+/// Turns the presses and releases of a binding into actions for its mode.
+struct BindingState {
+    mode: ShortcutMode,
+    hold_threshold: Duration,
+    pressed_at: Option<Instant>,
+    /// The current press started what the binding controls, so its release
+    /// may undo it. A recording started elsewhere is left alone.
+    started: bool,
+}
+
+impl BindingState {
+    fn new(binding: &ShortcutBinding, hold_threshold_ms: u64) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            mode: binding.mode,
+            hold_threshold: Duration::from_millis(hold_threshold_ms),
+            pressed_at: None,
+            started: false,
+        }))
+    }
+
+    /// Notes that the press being held started what the binding controls.
+    fn mark_started(&mut self) {
+        if self.pressed_at.is_some() {
+            self.started = true;
+        }
+    }
+
+    fn handle(&mut self, state: ShortcutState) -> Option<BindingAction> {
+        match (self.mode, state) {
+            (ShortcutMode::Toggle, ShortcutState::Pressed) => Some(BindingAction::Toggle),
+            (ShortcutMode::HoldToTalk, ShortcutState::Pressed) => {
+                // Ignore key repeat while the shortcut is held
+                if self.pressed_at.is_some() {
+                    return None;
+                }
+                self.pressed_at = Some(Instant::now());
+                Some(BindingAction::Start)
+            }
+            (ShortcutMode::HoldToTalk, ShortcutState::Released) => {
+                let pressed = self.pressed_at.take()?;
+                if !std::mem::take(&mut self.started) {
+                    return None;
+                }
+                if pressed.elapsed() < self.hold_threshold {
+                    Some(BindingAction::Tap)
+                } else {
+                    Some(BindingAction::Stop)
+                }
+            }
+            _ => None,
         }
     }
 }
@@ -46,51 +206,60 @@ impl<R: Runtime> ShortcutManager<R> {
 
     fn register_shortcuts(&self, config: &ShortcutConfig) -> tauri::Result<()> {
         let app = self.app.clone();
-        let transcription_shortcut = config.transcription.clone();
-        let edit_shortcut = config.edit.clone();
+        let transcription_shortcut = config.transcription.keys.clone();
+        let edit_shortcut = config.edit.keys.clone();
+        let pause_shortcut = config.pause.keys.clone();
 
         // Register transcription shortcut
         match transcription_shortcut.parse::<Shortcut>() {
             Ok(shortcut) => {
                 let app_clone = app.clone();
+                let binding = BindingState::new(&config.transcription, config.hold_threshold_ms);
                 match self.app.global_shortcut().on_shortcut(
                     shortcut,
                     move |_app_handle, _shortcut, event| {
-                        if event.state == ShortcutState::Pressed {
+                        let Some(action) = binding.lock().unwrap().handle(event.state) else {
+                            return;
+                        };
+
+                        if matches!(action, BindingAction::Toggle | BindingAction::Start) {
                             println!("Transcription shortcut triggered");
-                            // Emit event to frontend
-                            let _ = app_clone.emit("shortcut_triggered", "transcription");
+                            announce_transcription_shortcut(&app_clone);
+                        }
 
-                            // Show processing overlay
-                            if let Err(e) = app_clone.emit("show_processing_overlay", ()) {
-                                eprintln!("Failed to show processing overlay: {}", e);
-                            }
+                        let Some(recorder) = app_clone.try_state::<Arc<SimpleAudioRecorder>>()
+                        else {
+                            eprintln!("Audio recorder not available");
+                            let _ =
+                                app_clone.emit("recording_error", "Audio recorder not available");
+                            return;
+                        };
 
-                            // Toggle recording
-                            if let Some(recorder) =
-                                app_clone.try_state::<Arc<SimpleAudioRecorder>>()
-                            {
+                        match action {
+                            BindingAction::Toggle if recorder.is_recording() => {
+                                stop_recording(&app_clone, &recorder)
+                            }
+                            BindingAction::Toggle | BindingAction::Start => {
+                                if !recorder.is_recording()
+                                    && start_recording(&app_clone, &recorder)
+                                    && action == BindingAction::Start
+                                {
+                                    binding.lock().unwrap().mark_started();
+                                }
+                            }
+                            BindingAction::Stop => {
                                 if recorder.is_recording() {
-                                    if let Err(e) = recorder.stop_recording() {
-                                        eprintln!("Failed to stop recording: {}", e);
-                                        let _ = app_clone.emit(
-                                            "recording_error",
-                                            format!("Failed to stop recording: {}", e),
-                                        );
-                                    }
-                                } else {
-                                    if let Err(e) = recorder.start_recording() {
-                                        eprintln!("Failed to start recording: {}", e);
-                                        let _ = app_clone.emit(
-                                            "recording_error",
-                                            format!("Failed to start recording: {}", e),
-                                        );
+                                    stop_recording(&app_clone, &recorder);
+                                }
+                            }
+                            BindingAction::Tap => {
+                                // Accidental tap, throw away the audio it started
+                                if recorder.is_recording() {
+                                    println!("Transcription shortcut tapped, discarding recording");
+                                    if let Err(e) = recorder.cancel_recording() {
+                                        eprintln!("Failed to cancel recording: {}", e);
                                     }
                                 }
-                            } else {
-                                eprintln!("Audio recorder not available");
-                                let _ = app_clone
-                                    .emit("recording_error", "Audio recorder not available");
                             }
                         }
                     },
@@ -172,6 +341,78 @@ impl<R: Runtime> ShortcutManager<R> {
             }
         }
 
+        // Register pause shortcut
+        if pause_shortcut.trim().is_empty() {
+            return Ok(());
+        }
+        match pause_shortcut.parse::<Shortcut>() {
+            Ok(shortcut) => {
+                let app_clone = app.clone();
+                // Held in hold-to-talk mode, the recording pauses until release
+                let binding = BindingState::new(&config.pause, config.hold_threshold_ms);
+                match self.app.global_shortcut().on_shortcut(
+                    shortcut,
+                    move |_app_handle, _shortcut, event| {
+                        let Some(action) = binding.lock().unwrap().handle(event.state) else {
+                            return;
+                        };
+                        println!("Pause shortcut triggered");
+                        let _ = app_clone.emit("shortcut_triggered", "pause");
+
+                        let Some(recorder) = app_clone.try_state::<Arc<SimpleAudioRecorder>>()
+                        else {
+                            return;
+                        };
+                        if !recorder.is_recording() {
+                            return;
+                        }
+                        match action {
+                            BindingAction::Toggle => {
+                                set_paused(&app_clone, &recorder, !recorder.is_paused());
+                            }
+                            BindingAction::Start => {
+                                if !recorder.is_paused() && set_paused(&app_clone, &recorder, true)
+                                {
+                                    binding.lock().unwrap().mark_started();
+                                }
+                            }
+                            BindingAction::Stop | BindingAction::Tap => {
+                                if recorder.is_paused() {
+                                    set_paused(&app_clone, &recorder, false);
+                                }
+                            }
+                        }
+                    },
+                ) {
+                    Ok(_) => {
+                        println!(
+                            "Pause shortcut '{}' registered successfully",
+                            pause_shortcut
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "Failed to register pause shortcut '{}': {}",
+                            pause_shortcut, e
+                        );
+                        return Err(tauri::Error::Anyhow(anyhow::anyhow!(
+                            "Failed to register pause shortcut '{}': {}",
+                            pause_shortcut,
+                            e
+                        )));
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Invalid pause shortcut format '{}': {}", pause_shortcut, e);
+                return Err(tauri::Error::Anyhow(anyhow::anyhow!(
+                    "Invalid pause shortcut format '{}': {}",
+                    pause_shortcut,
+                    e
+                )));
+            }
+        }
+
         Ok(())
     }
 
@@ -227,6 +468,58 @@ impl<R: Runtime> ShortcutManager<R> {
     }
 }
 
+fn announce_transcription_shortcut<R: Runtime>(app: &AppHandle<R>) {
+    // Emit event to frontend
+    let _ = app.emit("shortcut_triggered", "transcription");
+
+    // Show processing overlay
+    if let Err(e) = app.emit("show_processing_overlay", ()) {
+        eprintln!("Failed to show processing overlay: {}", e);
+    }
+}
+
+/// Returns whether the recording started.
+fn start_recording<R: Runtime>(app: &AppHandle<R>, recorder: &SimpleAudioRecorder) -> bool {
+    if let Err(e) = recorder.start_recording() {
+        eprintln!("Failed to start recording: {}", e);
+        let _ = app.emit(
+            "recording_error",
+            format!("Failed to start recording: {}", e),
+        );
+        return false;
+    }
+    true
+}
+
+fn stop_recording<R: Runtime>(app: &AppHandle<R>, recorder: &SimpleAudioRecorder) {
+    if let Err(e) = recorder.stop_recording() {
+        eprintln!("Failed to stop recording: {}", e);
+        let _ = app.emit(
+            "recording_error",
+            format!("Failed to stop recording: {}", e),
+        );
+    }
+}
+
+/// Returns whether the recording was paused or resumed.
+fn set_paused<R: Runtime>(app: &AppHandle<R>, recorder: &SimpleAudioRecorder, paused: bool) -> bool {
+    let result = if paused {
+        recorder.pause_recording()
+    } else {
+        recorder.resume_recording()
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to pause or resume recording: {}", e);
+        let _ = app.emit(
+            "recording_error",
+            format!("Failed to pause or resume recording: {}", e),
+        );
+        return false;
+    }
+    true
+}
+
 // Global shortcut manager state
 pub type ShortcutManagerState = Arc<Mutex<ShortcutManager<tauri::Wry>>>;
 
@@ -255,3 +548,119 @@ pub fn validate_shortcut(shortcut: String) -> Result<bool, String> {
         Err(e) => Err(format!("Invalid shortcut format: {}", e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> ShortcutConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn hold(keys: &str) -> ShortcutBinding {
+        ShortcutBinding {
+            keys: keys.to_string(),
+            mode: ShortcutMode::HoldToTalk,
+        }
+    }
+
+    #[test]
+    fn reads_settings_saved_with_plain_keys() {
+        let config = parse(
+            r#"{"transcription": "Ctrl+Space", "edit": "Ctrl+E", "transcription_mode": "HoldToTalk"}"#,
+        );
+        assert_eq!(config.transcription, hold("Ctrl+Space"));
+        assert_eq!(config.edit, ShortcutBinding::toggle("Ctrl+E"));
+        assert_eq!(config.pause, default_pause_shortcut());
+        assert_eq!(config.hold_threshold_ms, default_hold_threshold_ms());
+
+        let config = parse(r#"{"transcription": "Ctrl+Space", "edit": "Ctrl+E", "transcription_mode": "Toggle"}"#);
+        assert_eq!(config.transcription, ShortcutBinding::toggle("Ctrl+Space"));
+
+        let config = parse(r#"{"transcription": "Ctrl+Space", "edit": "Ctrl+E"}"#);
+        assert_eq!(config.transcription, ShortcutBinding::toggle("Ctrl+Space"));
+    }
+
+    #[test]
+    fn reads_settings_with_bindings() {
+        let config = parse(
+            r#"{
+                "transcription": {"keys": "Ctrl+Space", "mode": "hold_to_talk"},
+                "edit": {"keys": "Ctrl+E"},
+                "pause": {"keys": "", "mode": "HoldToTalk"},
+                "hold_threshold_ms": 150
+            }"#,
+        );
+        assert_eq!(config.transcription, hold("Ctrl+Space"));
+        assert_eq!(config.edit, ShortcutBinding::toggle("Ctrl+E"));
+        assert_eq!(config.pause, hold(""));
+        assert_eq!(config.hold_threshold_ms, 150);
+
+        let saved = parse(&serde_json::to_string(&config).unwrap());
+        assert_eq!(saved.transcription, config.transcription);
+        assert_eq!(saved.edit, config.edit);
+        assert_eq!(saved.pause, config.pause);
+        assert_eq!(saved.hold_threshold_ms, config.hold_threshold_ms);
+    }
+
+    #[test]
+    fn toggle_acts_on_press_only() {
+        let state = BindingState::new(&ShortcutBinding::toggle("Ctrl+Space"), 300);
+        let mut state = state.lock().unwrap();
+        assert_eq!(state.handle(ShortcutState::Pressed), Some(BindingAction::Toggle));
+        assert_eq!(state.handle(ShortcutState::Released), None);
+        assert_eq!(state.handle(ShortcutState::Pressed), Some(BindingAction::Toggle));
+    }
+
+    #[test]
+    fn key_repeat_while_held_is_ignored() {
+        let state = BindingState::new(&hold("Ctrl+Space"), 60_000);
+        let mut state = state.lock().unwrap();
+        assert_eq!(state.handle(ShortcutState::Pressed), Some(BindingAction::Start));
+        state.mark_started();
+        assert_eq!(state.handle(ShortcutState::Pressed), None);
+        assert_eq!(state.handle(ShortcutState::Pressed), None);
+        assert_eq!(state.handle(ShortcutState::Released), Some(BindingAction::Tap));
+        assert_eq!(state.handle(ShortcutState::Pressed), Some(BindingAction::Start));
+    }
+
+    #[test]
+    fn release_before_the_threshold_is_a_tap() {
+        let state = BindingState::new(&hold("Ctrl+Space"), 60_000);
+        let mut state = state.lock().unwrap();
+        state.handle(ShortcutState::Pressed);
+        state.mark_started();
+        assert_eq!(state.handle(ShortcutState::Released), Some(BindingAction::Tap));
+    }
+
+    #[test]
+    fn release_after_the_threshold_stops() {
+        let state = BindingState::new(&hold("Ctrl+Space"), 20);
+        let mut state = state.lock().unwrap();
+        state.handle(ShortcutState::Pressed);
+        state.mark_started();
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(state.handle(ShortcutState::Released), Some(BindingAction::Stop));
+    }
+
+    #[test]
+    fn release_without_a_press_is_ignored() {
+        let state = BindingState::new(&hold("Ctrl+Space"), 0);
+        let mut state = state.lock().unwrap();
+        state.mark_started();
+        assert_eq!(state.handle(ShortcutState::Released), None);
+
+        // Nothing left over from the stray release
+        state.handle(ShortcutState::Pressed);
+        assert_eq!(state.handle(ShortcutState::Released), None);
+    }
+
+    #[test]
+    fn release_leaves_what_another_source_started() {
+        // Pressed while already recording, so this press started nothing
+        let state = BindingState::new(&hold("Ctrl+Space"), 0);
+        let mut state = state.lock().unwrap();
+        assert_eq!(state.handle(ShortcutState::Pressed), Some(BindingAction::Start));
+        assert_eq!(state.handle(ShortcutState::Released), None);
+    }
+}
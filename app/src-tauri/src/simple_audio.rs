@@ -1,314 +1,1776 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::StreamConfig;
+use cpal::traits::{DeviceTrait, HostTrait};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::audio_source::{AudioSource, SourceStream};
+use crate::capture_store::CaptureStore;
+use crate::chunk_queue::{AudioChunkQueue, ChunkHeader};
+use crate::dictation_session::{self, SessionTracker};
+use crate::dsp::{DspChain, DspConfig};
+use crate::error_handler::ErrorHandler;
+use crate::mic_test::{MicTestAnalyzer, MicTestReport};
+use crate::recording_archive::{self, ArchiveConfig};
+use crate::sound_cues::{Cue, CueGate};
+use crate::vad::{VadConfig, VadEvent, VoiceActivityDetector};
+use crate::websocket::{ProtocolMessage, SessionMode};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AudioDevice {
     pub name: String,
     pub is_default: bool,
 }
 
-pub struct SimpleAudioRecorder {
-    app: AppHandle<tauri::Wry>,
-    is_recording: Arc<Mutex<bool>>,
-    selected_device: Arc<Mutex<Option<String>>>,
-    audio_buffer: Arc<Mutex<VecDeque<f32>>>,
-    // Remove the stream storage since it's not Send/Sync safe
-    stream_config: Arc<Mutex<Option<StreamConfig>>>,
+/// Audio capture settings persisted in `AppSettings`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioConfig {
+    /// Sample rate of the PCM16 audio streamed to the backend.
+    pub target_sample_rate: u32,
+    /// Spoken language, passed to the backend when a session starts.
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Voice activity detection and auto-stop on trailing silence.
+    #[serde(default)]
+    pub vad: VadConfig,
+    /// High-pass, noise suppression and AGC applied before streaming.
+    #[serde(default)]
+    pub dsp: DspConfig,
+    /// Recording stops automatically after this many seconds (0 disables the limit).
+    #[serde(default = "default_max_session_secs")]
+    pub max_session_secs: u64,
+    /// How long before the limit a `recording_limit_warning` is emitted.
+    #[serde(default = "default_session_warning_secs")]
+    pub session_warning_secs: u64,
+    /// Keep the microphone open between recordings so speech from just before
+    /// the shortcut is not lost.
+    #[serde(default)]
+    pub warm_mic: bool,
+    /// Audio kept in memory while the mic is warm and prepended to the next
+    /// recording (milliseconds).
+    #[serde(default = "default_pre_roll_ms")]
+    pub pre_roll_ms: u64,
+    /// A second input recorded together with the microphone.
+    #[serde(default)]
+    pub loopback: LoopbackConfig,
+    /// Copies of finished sessions kept on disk.
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+}
+
+fn default_language() -> String {
+    "en-US".to_string()
+}
+
+fn default_max_session_secs() -> u64 {
+    300
+}
+
+fn default_session_warning_secs() -> u64 {
+    15
+}
+
+fn default_pre_roll_ms() -> u64 {
+    500
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            target_sample_rate: 16000,
+            language: default_language(),
+            vad: VadConfig::default(),
+            dsp: DspConfig::default(),
+            max_session_secs: default_max_session_secs(),
+            session_warning_secs: default_session_warning_secs(),
+            warm_mic: false,
+            pre_roll_ms: default_pre_roll_ms(),
+            loopback: LoopbackConfig::default(),
+            archive: ArchiveConfig::default(),
+        }
+    }
+}
+
+/// How the second input is combined with the microphone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMixMode {
+    /// Both inputs summed into one mono stream.
+    #[default]
+    Mix,
+    /// Stereo, with the microphone on the left and the second input on the right.
+    SeparateChannels,
+}
+
+/// A second input device captured alongside the microphone, typically a
+/// PulseAudio/PipeWire monitor source so both sides of a call are transcribed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoopbackConfig {
+    pub enabled: bool,
+    /// Name of the input device, as listed by `get_audio_devices`.
+    pub device: Option<String>,
+    pub mode: InputMixMode,
+}
+
+impl LoopbackConfig {
+    /// Channels in the streamed audio with this configuration.
+    pub fn output_channels(&self) -> u16 {
+        if self.enabled && self.mode == InputMixMode::SeparateChannels {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Format of the audio chunks emitted for streaming.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioFormat {
+    pub encoding: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Requests handled by the audio worker thread, which owns the cpal stream.
+enum AudioCommand {
+    Start {
+        reply: Sender<Result<String, String>>,
+    },
+    Stop {
+        reply: Sender<()>,
+    },
+    SwitchDevice {
+        reply: Sender<Result<Option<String>, String>>,
+    },
+    /// Opens or closes the idle warm-mic stream after a config change.
+    Refresh {
+        reply: Sender<Result<(), String>>,
+    },
+    /// Reopens the stream if its device has been unplugged, replying with the
+    /// lost and the new device name.
+    CheckDevice {
+        reply: Sender<Result<Option<(String, String)>, String>>,
+    },
+    /// Records from the selected device for a while and analyzes the signal.
+    Test {
+        duration: Duration,
+        reply: Sender<Result<MicTestReport, String>>,
+    },
+}
+
+/// Where the recorder reports to the rest of the app. Implemented for the
+/// app handle; tests can collect the events instead.
+pub trait RecorderEvents: Send + Sync + 'static {
+    /// Emits a UI event with its JSON payload.
+    fn emit_event(&self, event: &str, payload: serde_json::Value);
+    /// Adds an audio problem to the error log.
+    fn audio_error(&self, message: &str, details: Option<&str>);
+    /// Shows whether the mic is open without a recording running.
+    fn warm_mic_changed(&self, warm: bool);
+    /// Stops the recording through the regular stop path, off the calling thread.
+    fn request_stop(&self, reason: &'static str);
+    /// Keeps a copy of a finished session.
+    fn archive_recording(
+        &self,
+        config: ArchiveConfig,
+        session_id: Uuid,
+        samples: Vec<i16>,
+        format: AudioFormat,
+    );
+}
+
+impl dyn RecorderEvents {
+    fn emit<S: Serialize>(&self, event: &str, payload: S) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => self.emit_event(event, payload),
+            Err(e) => eprintln!("Failed to serialize '{}' event: {}", event, e),
+        }
+    }
+}
+
+impl RecorderEvents for AppHandle<tauri::Wry> {
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        let _ = Emitter::emit(self, event, payload);
+    }
+
+    fn audio_error(&self, message: &str, details: Option<&str>) {
+        ErrorHandler::new(self.clone()).handle_audio_error(message, details);
+    }
+
+    /// The tray is only touched from the main thread, which may itself be
+    /// waiting on the audio worker, so the update is posted rather than awaited.
+    fn warm_mic_changed(&self, warm: bool) {
+        let app = self.clone();
+        let posted = self.run_on_main_thread(move || {
+            if let Err(e) = crate::tray::set_warm_mic_indicator(&app, warm) {
+                eprintln!("Failed to update tray microphone indicator: {}", e);
+            }
+        });
+        if let Err(e) = posted {
+            eprintln!("Failed to update tray microphone indicator: {}", e);
+        }
+        let _ = Emitter::emit(self, "warm_mic_changed", warm);
+    }
+
+    /// Runs on its own thread since the input callback must not wait on the
+    /// worker that owns its stream.
+    fn request_stop(&self, reason: &'static str) {
+        let app = self.clone();
+        std::thread::spawn(move || {
+            if let Some(recorder) = app.try_state::<Arc<SimpleAudioRecorder>>() {
+                println!("{}, stopping recording", reason);
+                if let Err(e) = recorder.stop_recording() {
+                    eprintln!("Failed to stop recording automatically: {}", e);
+                    let _ = Emitter::emit(
+                        &app,
+                        "recording_error",
+                        format!("Failed to stop recording: {}", e),
+                    );
+                }
+            }
+        });
+    }
+
+    fn archive_recording(
+        &self,
+        config: ArchiveConfig,
+        session_id: Uuid,
+        samples: Vec<i16>,
+        format: AudioFormat,
+    ) {
+        recording_archive::archive_session(self, config, session_id, samples, format);
+    }
+}
+
+pub struct SimpleAudioRecorder {
+    events: Arc<dyn RecorderEvents>,
+    is_recording: Arc<Mutex<bool>>,
+    is_paused: Arc<Mutex<bool>>,
+    selected_device: Arc<Mutex<Option<String>>>,
+    active_device: Arc<Mutex<Option<String>>>,
+    audio_buffer: Arc<Mutex<VecDeque<i16>>>,
+    session: CaptureStore,
+    session_generation: Arc<AtomicU64>,
+    last_recording: Arc<Mutex<Vec<i16>>>,
+    config: Arc<Mutex<AudioConfig>>,
+    streamer: ChunkStreamer,
+    sessions: SessionTracker,
+    commands: Sender<AudioCommand>,
+}
+
+impl SimpleAudioRecorder {
+    /// `loopback_source` provides the optional second input.
+    pub fn new(
+        app: AppHandle<tauri::Wry>,
+        source: Box<dyn AudioSource>,
+        loopback_source: Box<dyn AudioSource>,
+        chunks: AudioChunkQueue,
+        cue_gate: CueGate,
+        sessions: SessionTracker,
+    ) -> Self {
+        Self::with_events(
+            Arc::new(app),
+            source,
+            loopback_source,
+            chunks,
+            cue_gate,
+            sessions,
+        )
+    }
+
+    pub fn with_events(
+        events: Arc<dyn RecorderEvents>,
+        source: Box<dyn AudioSource>,
+        loopback_source: Box<dyn AudioSource>,
+        chunks: AudioChunkQueue,
+        cue_gate: CueGate,
+        sessions: SessionTracker,
+    ) -> Self {
+        let is_recording = Arc::new(Mutex::new(false));
+        let is_paused = Arc::new(Mutex::new(false));
+        let selected_device = Arc::new(Mutex::new(None));
+        let audio_buffer = Arc::new(Mutex::new(VecDeque::new()));
+        let session = CaptureStore::new();
+        let session_generation = Arc::new(AtomicU64::new(0));
+        let config = Arc::new(Mutex::new(AudioConfig::default()));
+        let streamer = ChunkStreamer::new(chunks);
+
+        let context = CaptureContext {
+            events: events.clone(),
+            is_recording: is_recording.clone(),
+            is_paused: is_paused.clone(),
+            selected_device: selected_device.clone(),
+            audio_buffer: audio_buffer.clone(),
+            session: session.clone(),
+            session_generation: session_generation.clone(),
+            config: config.clone(),
+            streamer: streamer.clone(),
+            source,
+            loopback_source,
+            cue_gate,
+        };
+
+        let (commands, receiver) = channel();
+        std::thread::Builder::new()
+            .name("audio-capture".to_string())
+            .spawn(move || AudioWorker::new(context).run(receiver))
+            .expect("Failed to spawn audio worker thread");
+
+        Self {
+            events,
+            is_recording,
+            is_paused,
+            selected_device,
+            active_device: Arc::new(Mutex::new(None)),
+            audio_buffer,
+            session,
+            session_generation,
+            last_recording: Arc::new(Mutex::new(Vec::new())),
+            config,
+            streamer,
+            sessions,
+            commands,
+        }
+    }
+
+    pub fn start_recording(&self) -> Result<(), String> {
+        {
+            // Checked and set in one step, so overlapping starts cannot both
+            // begin a session. Held while the session is prepared, so the
+            // stream's first callback is already part of it.
+            let mut recording = self.is_recording.lock().unwrap();
+            if *recording {
+                return Err("Already recording".to_string());
+            }
+            *recording = true;
+
+            // Clear the audio buffers
+            {
+                let mut buffer = self.audio_buffer.lock().unwrap();
+                buffer.clear();
+            }
+            self.session.clear();
+            self.session_generation.fetch_add(1, Ordering::SeqCst);
+            self.streamer.start_session();
+            self.sessions.begin(self.streamer.session_id());
+            *self.is_paused.lock().unwrap() = false;
+
+            // Tell the backend what audio to expect before the first chunk
+            let format = self.output_format();
+            let language = self.config.lock().unwrap().language.clone();
+            self.streamer.send_control(ProtocolMessage::SessionStart {
+                session_id: self.streamer.session_id().to_string(),
+                encoding: format.encoding,
+                sample_rate: format.sample_rate,
+                channels: format.channels,
+                language,
+                mode: SessionMode::Dictation,
+            });
+        }
+
+        // The worker opens the device and starts the stream, or reuses the warm one
+        let device_name = match self.send_command(|reply| AudioCommand::Start { reply }) {
+            Ok(Ok(device_name)) => device_name,
+            Ok(Err(e)) | Err(e) => {
+                *self.is_recording.lock().unwrap() = false;
+                self.end_session(true);
+                return Err(e);
+            }
+        };
+
+        // Remember which device is actually live
+        {
+            let mut active = self.active_device.lock().unwrap();
+            *active = Some(device_name);
+        }
+
+        // Emit recording started event
+        self.events.emit("recording_started", ());
+
+        println!("Recording started");
+
+        Ok(())
+    }
+
+    pub fn stop_recording(&self) -> Result<(), String> {
+        if !self.end_capture()? {
+            return Ok(());
+        }
+
+        // Stream whatever is left of the last partial chunk
+        let tail: Vec<i16> = self.audio_buffer.lock().unwrap().drain(..).collect();
+        if !tail.is_empty() {
+            self.streamer
+                .send(&*self.events, &tail, self.output_format().channels);
+        }
+        self.end_session(false);
+
+        // Collect the complete utterance
+        let audio_data = self
+            .session
+            .take_all()
+            .map_err(|e| format!("Failed to read recorded audio: {}", e))?;
+
+        let archive = self.config.lock().unwrap().archive.clone();
+        if archive.enabled && !audio_data.is_empty() {
+            self.events.archive_recording(
+                archive,
+                self.streamer.session_id(),
+                audio_data.clone(),
+                self.output_format(),
+            );
+        }
+
+        let captured = audio_data.len();
+        *self.last_recording.lock().unwrap() = audio_data;
+
+        // The audio itself already went to the backend chunk by chunk
+        self.events.emit("recording_stopped", ());
+
+        println!("Recording stopped, {} samples captured", captured);
+
+        Ok(())
+    }
+
+    /// Stops recording and throws the captured audio away.
+    pub fn cancel_recording(&self) -> Result<(), String> {
+        if !self.end_capture()? {
+            return Ok(());
+        }
+
+        self.audio_buffer.lock().unwrap().clear();
+        self.session.clear();
+        self.end_session(true);
+        self.events.emit("recording_cancelled", ());
+
+        println!("Recording cancelled");
+
+        Ok(())
+    }
+
+    /// Sends `session_end`. Unless the session was cancelled, the result is
+    /// emitted on `session_finished` once the backend completes it.
+    fn end_session(&self, cancelled: bool) {
+        let session_id = self.streamer.session_id();
+        if cancelled {
+            self.sessions.cancel();
+        } else {
+            let events = self.events.clone();
+            self.sessions.finish(
+                session_id,
+                dictation_session::COMPLETE_TIMEOUT,
+                move |outcome| events.emit("session_finished", &outcome),
+            );
+        }
+        let queued = self.streamer.send_control(ProtocolMessage::SessionEnd {
+            session_id: session_id.to_string(),
+            cancelled,
+        });
+        // Without a connection nothing will ever answer
+        if !queued && !cancelled {
+            self.sessions.abandon();
+        }
+    }
+
+    /// Clears the recording flag and releases the device. Returns false if
+    /// nothing was being recorded.
+    fn end_capture(&self) -> Result<bool, String> {
+        {
+            let mut recording = self.is_recording.lock().unwrap();
+            if !*recording {
+                return Ok(false);
+            }
+
+            // Set recording flag to false
+            *recording = false;
+        }
+        *self.is_paused.lock().unwrap() = false;
+
+        // Release the device, unless the mic is kept warm
+        self.send_command(|reply| AudioCommand::Stop { reply })?;
+
+        // No device is live any more
+        {
+            let mut active = self.active_device.lock().unwrap();
+            *active = None;
+        }
+
+        Ok(true)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        *self.is_recording.lock().unwrap()
+    }
+
+    /// Stops capturing without ending the session. Audio recorded so far is
+    /// kept and the session continues on `resume_recording`.
+    pub fn pause_recording(&self) -> Result<(), String> {
+        if !self.is_recording() {
+            return Err("Not recording".to_string());
+        }
+
+        {
+            let mut paused = self.is_paused.lock().unwrap();
+            if *paused {
+                return Ok(());
+            }
+            *paused = true;
+        }
+
+        // Stream the partial chunk captured before the pause
+        let tail: Vec<i16> = self.audio_buffer.lock().unwrap().drain(..).collect();
+        if !tail.is_empty() {
+            self.streamer
+                .send(&*self.events, &tail, self.output_format().channels);
+        }
+
+        self.events.emit("recording_paused", ());
+        println!("Recording paused");
+
+        Ok(())
+    }
+
+    pub fn resume_recording(&self) -> Result<(), String> {
+        if !self.is_recording() {
+            return Err("Not recording".to_string());
+        }
+
+        {
+            let mut paused = self.is_paused.lock().unwrap();
+            if !*paused {
+                return Ok(());
+            }
+            *paused = false;
+        }
+
+        self.events.emit("recording_resumed", ());
+        println!("Recording resumed");
+
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.is_paused.lock().unwrap()
+    }
+
+    pub fn select_device(&self, device_name: &str) -> Result<(), String> {
+        {
+            let mut selected = self.selected_device.lock().unwrap();
+            *selected = Some(device_name.to_string());
+        }
+
+        // Move a running capture over to the newly selected device
+        if let Some(device_name) =
+            self.send_command(|reply| AudioCommand::SwitchDevice { reply })??
+        {
+            if !self.is_recording() {
+                return Ok(());
+            }
+            let mut active = self.active_device.lock().unwrap();
+            *active = Some(device_name);
+        }
+
+        Ok(())
+    }
+
+    /// Moves capture to another device, normally the default one, if the
+    /// device it is open on has disappeared.
+    pub fn recover_lost_device(&self) -> Result<Option<(String, String)>, String> {
+        let switched = self.send_command(|reply| AudioCommand::CheckDevice { reply })??;
+
+        if let Some((_, device_name)) = &switched {
+            if self.is_recording() {
+                let mut active = self.active_device.lock().unwrap();
+                *active = Some(device_name.clone());
+            }
+        }
+
+        Ok(switched)
+    }
+
+    pub fn active_device(&self) -> Option<String> {
+        self.active_device.lock().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: AudioConfig) -> Result<(), String> {
+        {
+            let mut current_config = self.config.lock().unwrap();
+            *current_config = config;
+        }
+
+        // Open or close the warm mic to match the new settings
+        self.send_command(|reply| AudioCommand::Refresh { reply })?
+    }
+
+    /// The format of the streamed chunks and of archived recordings.
+    pub fn output_format(&self) -> AudioFormat {
+        let config = self.config.lock().unwrap();
+        AudioFormat {
+            encoding: "linear16".to_string(),
+            sample_rate: config.target_sample_rate,
+            channels: config.loopback.output_channels(),
+        }
+    }
+
+    fn send_command<T>(
+        &self,
+        command: impl FnOnce(Sender<T>) -> AudioCommand,
+    ) -> Result<T, String> {
+        let (reply, response) = channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| "Audio worker is not running".to_string())?;
+        response
+            .recv()
+            .map_err(|_| "Audio worker stopped unexpectedly".to_string())
+    }
+
+    /// Records from the selected device for `duration` and reports on the
+    /// quality of the signal. Blocks until the test is done.
+    pub fn test_microphone(&self, duration: Duration) -> Result<MicTestReport, String> {
+        if self.is_recording() {
+            return Err("Stop recording before testing the microphone".to_string());
+        }
+        self.send_command(|reply| AudioCommand::Test { duration, reply })?
+    }
+
+    /// The complete audio of the last finished recording.
+    pub fn get_audio_data(&self) -> Vec<i16> {
+        self.last_recording.lock().unwrap().clone()
+    }
+}
+
+/// Everything the worker thread needs to open a capture stream.
+struct CaptureContext {
+    events: Arc<dyn RecorderEvents>,
+    is_recording: Arc<Mutex<bool>>,
+    is_paused: Arc<Mutex<bool>>,
+    selected_device: Arc<Mutex<Option<String>>>,
+    audio_buffer: Arc<Mutex<VecDeque<i16>>>,
+    session: CaptureStore,
+    session_generation: Arc<AtomicU64>,
+    config: Arc<Mutex<AudioConfig>>,
+    streamer: ChunkStreamer,
+    source: Box<dyn AudioSource>,
+    /// Where the optional second input comes from.
+    loopback_source: Box<dyn AudioSource>,
+    cue_gate: CueGate,
+}
+
+/// The streams the worker keeps open while capturing.
+struct CaptureStreams {
+    microphone: Box<dyn SourceStream>,
+    loopback: Option<Box<dyn SourceStream>>,
+    device_name: String,
+    /// Shared with the microphone callback, so the worker can finish a
+    /// session once the callback stops delivering.
+    sink: Arc<Mutex<CaptureSink>>,
+}
+
+impl CaptureContext {
+    /// Opens the selected device on the audio source and starts a stream
+    /// feeding the shared buffer. Falls back to the default device if the
+    /// selected one is no longer connected.
+    fn open_stream(&mut self) -> Result<CaptureStreams, String> {
+        let selected = self.selected_device.lock().unwrap().clone();
+        let format = self.source.prepare(selected.as_deref())?;
+
+        if let Some(name) = format.fallback_from.as_deref() {
+            let message = format!(
+                "Selected microphone '{}' is not available, using the default input device",
+                name
+            );
+            eprintln!("{}", message);
+            self.events.emit("audio_error", &message);
+            self.events.audio_error(&message, Some(name));
+        }
+
+        println!(
+            "Recording from '{}' with sample rate: {}, channels: {}",
+            format.device_name, format.sample_rate, format.channels
+        );
+
+        // Convert to the streaming format and emit 100ms chunks
+        let config = self.config.lock().unwrap().clone();
+        let target_sample_rate = config.target_sample_rate;
+        let mut sink = CaptureSink::new(
+            self,
+            PcmConverter::new(format.sample_rate, format.channels, target_sample_rate),
+            LevelMeter::new(config.vad.speech_threshold_db),
+            target_sample_rate,
+            config.loopback.output_channels(),
+        );
+        let mut loopback = None;
+        if config.loopback.enabled {
+            let pending = Arc::new(Mutex::new(VecDeque::new()));
+            loopback = self.open_loopback(&config, pending.clone());
+            sink = sink.with_loopback(pending, config.loopback.mode);
+        }
+        if config.dsp.is_enabled() {
+            sink = sink.with_dsp(DspChain::new(&config.dsp, target_sample_rate));
+        }
+        if config.vad.enabled {
+            sink = sink.with_vad(VoiceActivityDetector::new(config.vad, target_sample_rate));
+        }
+        if config.max_session_secs > 0 {
+            sink = sink.with_session_limit(config.max_session_secs, config.session_warning_secs);
+        }
+        if config.warm_mic && config.pre_roll_ms > 0 {
+            sink = sink.with_pre_roll(config.pre_roll_ms, self.session_generation.clone());
+        }
+        let events = self.events.clone();
+        let error_callback = move |err: String| {
+            eprintln!("Audio input error: {}", err);
+            events.emit("audio_error", err);
+        };
+
+        let sink = Arc::new(Mutex::new(sink));
+        let callback_sink = sink.clone();
+        let microphone = self.source.start(
+            Box::new(move |data: &[f32], captured_at| {
+                callback_sink
+                    .lock()
+                    .unwrap()
+                    .push(data.iter().copied(), captured_at)
+            }),
+            Box::new(error_callback),
+        )?;
+
+        Ok(CaptureStreams {
+            microphone,
+            loopback,
+            device_name: format.device_name,
+            sink,
+        })
+    }
+
+    /// Captures raw device samples for `duration` and analyzes them.
+    fn test_microphone(&mut self, duration: Duration) -> Result<MicTestReport, String> {
+        let selected = self.selected_device.lock().unwrap().clone();
+        let format = self.source.prepare(selected.as_deref())?;
+        println!(
+            "Testing microphone '{}' for {:?}",
+            format.device_name, duration
+        );
+
+        let (samples, received) = channel::<Vec<f32>>();
+        let stream = self.source.start(
+            Box::new(move |data: &[f32], _| {
+                let _ = samples.send(data.to_vec());
+            }),
+            Box::new(|err: String| eprintln!("Microphone test error: {}", err)),
+        )?;
+
+        let mut analyzer = MicTestAnalyzer::new(format);
+        let deadline = Instant::now() + duration;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match received.recv_timeout(remaining) {
+                Ok(data) => analyzer.push(&data),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        if let Err(e) = stream.pause() {
+            eprintln!("Failed to pause audio stream: {}", e);
+        }
+        drop(stream);
+
+        analyzer.report()
+    }
+
+    /// Starts the second input, converting it to the output format into
+    /// `pending` for the microphone sink to pick up. Recording carries on
+    /// from the microphone alone if the device cannot be opened.
+    fn open_loopback(
+        &mut self,
+        config: &AudioConfig,
+        pending: Arc<Mutex<VecDeque<i16>>>,
+    ) -> Option<Box<dyn SourceStream>> {
+        let result = match config.loopback.device.as_deref() {
+            Some(name) if self.loopback_source.is_available(name) => {
+                self.loopback_source.prepare(Some(name))
+            }
+            Some(name) => Err(format!("Device '{}' is not connected", name)),
+            None => Err("No device selected".to_string()),
+        }
+        .and_then(|format| {
+            println!(
+                "Recording second input from '{}' with sample rate: {}, channels: {}",
+                format.device_name, format.sample_rate, format.channels
+            );
+
+            let mut feed = LoopbackFeed {
+                converter: PcmConverter::new(
+                    format.sample_rate,
+                    format.channels,
+                    config.target_sample_rate,
+                ),
+                converted: Vec::new(),
+                pending,
+                capacity: config.target_sample_rate as usize / 4, // 250ms
+            };
+            let events = self.events.clone();
+            self.loopback_source.start(
+                Box::new(move |data: &[f32], _| feed.push(data.iter().copied())),
+                Box::new(move |err: String| {
+                    eprintln!("Second audio input error: {}", err);
+                    events.emit("audio_error", err);
+                }),
+            )
+        });
+
+        match result {
+            Ok(stream) => Some(stream),
+            Err(e) => {
+                let message =
+                    "Could not open the second audio input, recording the microphone only";
+                self.events.emit("audio_error", message);
+                self.events.audio_error(message, Some(&e));
+                None
+            }
+        }
+    }
+}
+
+/// Converts the second input to the output format as it arrives and queues
+/// it for the microphone sink. Keeps only a short backlog, so the two inputs
+/// cannot drift apart when their device clocks differ.
+struct LoopbackFeed {
+    converter: PcmConverter,
+    converted: Vec<i16>,
+    pending: Arc<Mutex<VecDeque<i16>>>,
+    capacity: usize,
+}
+
+impl LoopbackFeed {
+    fn push(&mut self, samples: impl IntoIterator<Item = f32>) {
+        self.converted.clear();
+        self.converter.process(samples, &mut self.converted);
+
+        let mut pending = self.pending.lock().unwrap();
+        pending.extend(self.converted.iter());
+        let excess = pending.len().saturating_sub(self.capacity);
+        pending.drain(..excess);
+    }
+}
+
+/// Combines the microphone with the audio queued by a `LoopbackFeed`. The
+/// microphone sets the pace; missing second-input audio is filled with silence.
+struct LoopbackMix {
+    pending: Arc<Mutex<VecDeque<i16>>>,
+    mode: InputMixMode,
+    combined: Vec<i16>,
+}
+
+impl LoopbackMix {
+    fn combine(&mut self, microphone: &mut Vec<i16>) {
+        let mut pending = self.pending.lock().unwrap();
+        let available = pending.len().min(microphone.len());
+        let mut other = pending
+            .drain(..available)
+            .chain(std::iter::repeat(0))
+            .take(microphone.len());
+
+        match self.mode {
+            InputMixMode::Mix => {
+                for (sample, other) in microphone.iter_mut().zip(other) {
+                    *sample = sample.saturating_add(other);
+                }
+            }
+            InputMixMode::SeparateChannels => {
+                self.combined.clear();
+                for &sample in microphone.iter() {
+                    self.combined.push(sample);
+                    self.combined.push(other.next().unwrap_or(0));
+                }
+                std::mem::swap(microphone, &mut self.combined);
+            }
+        }
+    }
+
+    /// Drops second-input audio that arrived while the microphone was ignored.
+    fn clear(&mut self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+/// Receives normalized device samples from the input callback, converts them
+/// and streams them in 100ms chunks. While the mic is warm and nothing is being
+/// recorded, the latest audio only goes to the in-memory pre-roll ring.
+struct CaptureSink {
+    events: Arc<dyn RecorderEvents>,
+    is_recording: Arc<Mutex<bool>>,
+    is_paused: Arc<Mutex<bool>>,
+    was_paused: bool,
+    was_recording: bool,
+    buffer: Arc<Mutex<VecDeque<i16>>>,
+    streamer: ChunkStreamer,
+    session: CaptureStore,
+    pre_roll: Option<PreRoll>,
+    converter: PcmConverter,
+    converted: Vec<i16>,
+    dsp: Option<DspChain>,
+    processed: Vec<i16>,
+    loopback: Option<LoopbackMix>,
+    cue_gate: CueGate,
+    vad: Option<VoiceActivityDetector>,
+    limit: Option<SessionLimit>,
+    meter: LevelMeter,
+    gaps: GapDetector,
+    /// Capture time just past the last block pushed.
+    captured_until: Instant,
+    output_rate: u32,
+    channels: u16,
+    chunk_size: usize,
+    stop_requested: bool,
+}
+
+/// Ring of the most recent idle audio while the mic is warm. It is only ever
+/// read when a recording starts.
+struct PreRoll {
+    samples: VecDeque<i16>,
+    capacity: usize,
+    session_generation: Arc<AtomicU64>,
+    current_generation: Option<u64>,
+}
+
+impl PreRoll {
+    /// Keeps only the most recent `capacity` samples.
+    fn fill(&mut self, samples: &[i16]) {
+        self.samples.extend(samples.iter());
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+
+    fn is_new_session(&mut self) -> bool {
+        let generation = self.session_generation.load(Ordering::SeqCst);
+        let is_new = self.current_generation != Some(generation);
+        self.current_generation = Some(generation);
+        is_new
+    }
+
+    fn take(&mut self) -> Vec<i16> {
+        self.samples.drain(..).collect()
+    }
+}
+
+/// Maximum session length, in output samples.
+struct SessionLimit {
+    max_samples: usize,
+    warning_samples: usize,
+    warned: bool,
+}
+
+impl CaptureSink {
+    fn new(
+        context: &CaptureContext,
+        converter: PcmConverter,
+        meter: LevelMeter,
+        output_rate: u32,
+        channels: u16,
+    ) -> Self {
+        Self {
+            events: context.events.clone(),
+            is_recording: context.is_recording.clone(),
+            is_paused: context.is_paused.clone(),
+            was_paused: false,
+            was_recording: false,
+            buffer: context.audio_buffer.clone(),
+            streamer: context.streamer.clone(),
+            session: context.session.clone(),
+            pre_roll: None,
+            converter,
+            converted: Vec::new(),
+            dsp: None,
+            processed: Vec::new(),
+            loopback: None,
+            cue_gate: context.cue_gate.clone(),
+            vad: None,
+            limit: None,
+            meter,
+            gaps: GapDetector::new(output_rate),
+            captured_until: Instant::now(),
+            output_rate,
+            channels,
+            chunk_size: output_rate as usize / 10 * channels as usize, // 100ms
+            stop_requested: false,
+        }
+    }
+
+    fn with_dsp(mut self, dsp: DspChain) -> Self {
+        self.dsp = Some(dsp);
+        self
+    }
+
+    fn with_loopback(mut self, pending: Arc<Mutex<VecDeque<i16>>>, mode: InputMixMode) -> Self {
+        self.loopback = Some(LoopbackMix {
+            pending,
+            mode,
+            combined: Vec::new(),
+        });
+        self
+    }
+
+    fn with_vad(mut self, vad: VoiceActivityDetector) -> Self {
+        self.vad = Some(vad);
+        self
+    }
+
+    fn with_session_limit(mut self, max_secs: u64, warning_secs: u64) -> Self {
+        let rate = self.samples_per_sec();
+        let max_samples = max_secs as usize * rate;
+        self.limit = Some(SessionLimit {
+            max_samples,
+            warning_samples: max_samples.saturating_sub(warning_secs as usize * rate),
+            warned: false,
+        });
+        self
+    }
+
+    /// Keeps the sink alive between recordings; `session_generation` tells it
+    /// when a new recording has started.
+    fn with_pre_roll(mut self, pre_roll_ms: u64, session_generation: Arc<AtomicU64>) -> Self {
+        let capacity = pre_roll_ms as usize * self.samples_per_sec() / 1000;
+        self.pre_roll = Some(PreRoll {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            session_generation,
+            current_generation: None,
+        });
+        self
+    }
+
+    /// Takes interleaved samples in the range [-1.0, 1.0], the first of them
+    /// captured at `captured_at`.
+    fn push(&mut self, samples: impl IntoIterator<Item = f32>, captured_at: Instant) {
+        // Only process audio if we're recording or keeping a pre-roll
+        let recording = *self.is_recording.lock().unwrap();
+        if recording {
+            if !self.was_recording {
+                self.was_recording = true;
+                self.gaps.reset();
+            }
+        } else {
+            self.finish_session();
+        }
+        if !recording && self.pre_roll.is_none() {
+            return;
+        }
+
+        // Drop audio while paused; the session stays open
+        if recording && *self.is_paused.lock().unwrap() {
+            self.was_paused = true;
+            return;
+        }
+        if self.was_paused {
+            self.was_paused = false;
+            self.gaps.reset();
+            if let Some(loopback) = self.loopback.as_mut() {
+                loopback.clear();
+            }
+            if let Some(vad) = self.vad.as_mut() {
+                vad.reset();
+            }
+        }
+
+        self.converted.clear();
+        self.converter.process(samples, &mut self.converted);
+        let captured_samples = self.converted.len();
+        self.captured_until = captured_at
+            + Duration::from_secs_f64(
+                captured_samples as f64 / self.samples_per_sec().max(1) as f64,
+            );
+
+        if let Some(dsp) = self.dsp.as_mut() {
+            self.processed.clear();
+            dsp.process(&self.converted, &mut self.processed);
+            std::mem::swap(&mut self.converted, &mut self.processed);
+        }
+
+        if let Some(loopback) = self.loopback.as_mut() {
+            loopback.combine(&mut self.converted);
+        }
+
+        // Keep our own sound cues out of the recording
+        self.gate_cues(captured_at, recording);
+
+        if let Some(pre_roll) = self.pre_roll.as_mut() {
+            if !recording {
+                pre_roll.fill(&self.converted);
+                return;
+            }
+            if pre_roll.is_new_session() {
+                let mut samples = pre_roll.take();
+                samples.extend_from_slice(&self.converted);
+                self.converted = samples;
+                self.reset_session_state();
+            }
+        }
+
+        // Dropped input callbacks leave a hole in the session timeline
+        if let Some(missing) = self.gaps.check(captured_at, captured_samples) {
+            self.report_gap(missing);
+        }
+
+        if let Some(vad) = self.vad.as_mut() {
+            // The detector listens to both inputs together
+            let mono = if self.channels > 1 {
+                self.processed.clear();
+                self.processed
+                    .extend(self.converted.chunks(self.channels as usize).map(|frame| {
+                        (frame.iter().map(|&s| s as i32).sum::<i32>() / frame.len() as i32) as i16
+                    }));
+                &self.processed
+            } else {
+                &self.converted
+            };
+            match vad.process(mono) {
+                Some(VadEvent::SpeechStarted) => {
+                    self.events.emit("speech_started", ());
+                }
+                Some(VadEvent::SpeechEnded) => {
+                    self.events.emit("speech_ended", ());
+                    self.request_stop("Trailing silence detected");
+                }
+                None => {}
+            }
+        }
+
+        // Keep the whole session for the final utterance
+        self.session.append(&self.converted);
+        self.check_session_limit(self.session.len());
+
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.extend(self.converted.iter());
+
+        // Stream audio data whenever a full chunk is available
+        while self.chunk_size > 0 && buffer.len() >= self.chunk_size {
+            let chunk: Vec<i16> = buffer.drain(..self.chunk_size).collect();
+            self.streamer.send(&*self.events, &chunk, self.channels);
+
+            if let Some(level) = self.meter.measure(&chunk) {
+                self.events.emit("audio_level", &level);
+            }
+        }
+    }
+
+    /// Adds the audio still held by the DSP chain to the session that just
+    /// ended, ahead of the last partial chunk the recorder streams.
+    fn finish_session(&mut self) {
+        if !std::mem::take(&mut self.was_recording) {
+            return;
+        }
+        let Some(dsp) = self.dsp.as_mut() else {
+            return;
+        };
+
+        self.converted.clear();
+        dsp.flush(&mut self.converted);
+        if self.converted.is_empty() {
+            return;
+        }
+        if let Some(loopback) = self.loopback.as_mut() {
+            loopback.combine(&mut self.converted);
+        }
+        self.gate_cues(self.captured_until, true);
+        self.session.append(&self.converted);
+        self.buffer.lock().unwrap().extend(self.converted.iter());
+    }
+
+    /// Silences the converted samples, captured from `captured_at`, where
+    /// they overlap a cue that would otherwise end up in the recording.
+    fn gate_cues(&mut self, captured_at: Instant, recording: bool) {
+        let pre_roll = self.pre_roll.is_some();
+        self.cue_gate.mute(
+            captured_at,
+            self.output_rate,
+            self.channels,
+            &mut self.converted,
+            |cue| cue_is_gated(cue, pre_roll, recording),
+        );
+    }
+
+    fn samples_per_sec(&self) -> usize {
+        self.output_rate as usize * self.channels as usize
+    }
+
+    /// Clears what the previous recording left behind in a reused sink.
+    fn reset_session_state(&mut self) {
+        self.stop_requested = false;
+        self.gaps.reset();
+        if let Some(limit) = self.limit.as_mut() {
+            limit.warned = false;
+        }
+        if let Some(vad) = self.vad.as_mut() {
+            vad.reset();
+        }
+    }
+
+    /// Streams the partial chunk captured before the gap, so every chunk
+    /// stays contiguous, then moves the session timeline past the gap. The
+    /// kept session gets silence in its place, so it lines up with the
+    /// timeline too.
+    fn report_gap(&mut self, missing_samples: u64) {
+        let partial: Vec<i16> = self.buffer.lock().unwrap().drain(..).collect();
+        if !partial.is_empty() {
+            self.streamer.send(&*self.events, &partial, self.channels);
+        }
+
+        let (session_id, sample_offset) = self.streamer.skip(missing_samples);
+        self.session
+            .append_silence(missing_samples as usize * self.channels as usize);
+        let gap = AudioGap {
+            session_id: session_id.to_string(),
+            sample_offset,
+            missing_samples,
+            missing_ms: missing_samples * 1000 / self.output_rate.max(1) as u64,
+        };
+        eprintln!(
+            "Audio input dropped {}ms at sample {}",
+            gap.missing_ms, gap.sample_offset
+        );
+        self.events.emit("audio_gap", &gap);
+    }
+
+    fn check_session_limit(&mut self, captured: usize) {
+        let Some(limit) = self.limit.as_mut() else {
+            return;
+        };
+
+        if captured >= limit.max_samples {
+            if !self.stop_requested {
+                self.events.emit("recording_limit_reached", ());
+            }
+            self.request_stop("Maximum recording length reached");
+        } else if !limit.warned && captured >= limit.warning_samples {
+            limit.warned = true;
+            let remaining_secs = (limit.max_samples - captured) / self.samples_per_sec().max(1);
+            self.events.emit("recording_limit_warning", remaining_secs);
+        }
+    }
+
+    fn request_stop(&mut self, reason: &'static str) {
+        if !self.stop_requested {
+            self.stop_requested = true;
+            self.events.request_stop(reason);
+        }
+    }
+}
+
+/// Whether input overlapping `cue` is silenced, given whether the sink keeps
+/// a pre-roll and whether a session is being recorded.
+fn cue_is_gated(cue: Cue, pre_roll: bool, recording: bool) -> bool {
+    match cue {
+        // With a pre-roll the user may already be talking over it
+        Cue::Start => !pre_roll,
+        // Losing speech to an error beep is worse than keeping it
+        Cue::Error => !recording,
+        Cue::Stop | Cue::Paste => true,
+    }
 }
 
-impl SimpleAudioRecorder {
-    pub fn new(app: AppHandle<tauri::Wry>) -> Self {
+/// Notices when the input stream delivered less audio than its capture
+/// timestamps span, which means callbacks were dropped.
+struct GapDetector {
+    sample_rate: u32,
+    /// Shortfall put down to timestamp jitter rather than lost audio.
+    tolerance: u64,
+    /// Capture time of the first block counted.
+    started: Option<Instant>,
+    received: u64,
+}
+
+impl GapDetector {
+    fn new(sample_rate: u32) -> Self {
         Self {
-            app,
-            is_recording: Arc::new(Mutex::new(false)),
-            selected_device: Arc::new(Mutex::new(None)),
-            audio_buffer: Arc::new(Mutex::new(VecDeque::new())),
-            stream_config: Arc::new(Mutex::new(None)),
+            sample_rate,
+            tolerance: sample_rate as u64 / 20, // 50ms
+            started: None,
+            received: 0,
         }
     }
 
-    pub fn start_recording(&self) -> Result<(), String> {
-        let mut recording = self.is_recording.lock().unwrap();
-        if *recording {
-            return Err("Already recording".to_string());
+    fn reset(&mut self) {
+        self.started = None;
+        self.received = 0;
+    }
+
+    /// Counts a block of samples captured at `captured_at` and returns how
+    /// many samples went missing before it, if any.
+    fn check(&mut self, captured_at: Instant, samples: usize) -> Option<u64> {
+        let Some(started) = self.started else {
+            self.started = Some(captured_at);
+            self.received = samples as u64;
+            return None;
+        };
+
+        // Where this block starts on the timeline, going by its timestamp
+        let expected = (captured_at.saturating_duration_since(started).as_secs_f64()
+            * self.sample_rate as f64)
+            .round() as u64;
+        if expected <= self.received + self.tolerance {
+            self.received += samples as u64;
+            return None;
         }
 
-        // Clear the audio buffer
-        {
-            let mut buffer = self.audio_buffer.lock().unwrap();
-            buffer.clear();
+        let missing = expected - self.received;
+        self.received = expected + samples as u64;
+        Some(missing)
+    }
+}
+
+/// Position of the current session in the stream of chunks.
+struct StreamPosition {
+    session_id: Uuid,
+    sequence: u64,
+    sample_offset: u64,
+}
+
+impl StreamPosition {
+    fn new() -> Self {
+        Self {
+            session_id: Uuid::new_v4(),
+            sequence: 0,
+            sample_offset: 0,
         }
+    }
+}
 
-        // Get the default audio host
-        let host = cpal::default_host();
+/// Numbers the chunks of each session and hands them to the WebSocket
+/// writer. Shared by the capture thread and the recorder, which streams the
+/// last partial chunk.
+#[derive(Clone)]
+struct ChunkStreamer {
+    queue: AudioChunkQueue,
+    position: Arc<Mutex<StreamPosition>>,
+}
 
-        // Get the default input device
-        let device = host
-            .default_input_device()
-            .ok_or("No default input device found")?;
+impl ChunkStreamer {
+    fn new(queue: AudioChunkQueue) -> Self {
+        Self {
+            queue,
+            position: Arc::new(Mutex::new(StreamPosition::new())),
+        }
+    }
 
-        // Get the default input configuration
-        let config = device
-            .default_input_config()
-            .map_err(|e| format!("Failed to get default input config: {}", e))?;
+    /// Gives the next recording its own session ID and restarts numbering.
+    fn start_session(&self) {
+        *self.position.lock().unwrap() = StreamPosition::new();
+    }
 
-        let sample_rate = config.sample_rate().0;
-        let channels = config.channels();
-        let sample_format = config.sample_format();
-        let stream_config: StreamConfig = config.into();
+    fn session_id(&self) -> Uuid {
+        self.position.lock().unwrap().session_id
+    }
 
-        println!(
-            "Recording with sample rate: {}, channels: {}",
-            sample_rate, channels
-        );
+    /// Streams a chunk of interleaved samples and reports it to the UI on
+    /// `audio_chunk`.
+    fn send(&self, events: &dyn RecorderEvents, chunk: &[i16], channels: u16) {
+        let header = {
+            let mut position = self.position.lock().unwrap();
+            let header = ChunkHeader {
+                session_id: position.session_id,
+                sequence: position.sequence,
+                sample_offset: position.sample_offset,
+            };
+            position.sequence += 1;
+            position.sample_offset += (chunk.len() / channels.max(1) as usize) as u64;
+            header
+        };
 
-        // Store config for later use
-        {
-            let mut stored_config = self.stream_config.lock().unwrap();
-            *stored_config = Some(stream_config.clone());
-        }
-
-        // Create a buffer to hold the audio data
-        let buffer = self.audio_buffer.clone();
-        let app = self.app.clone();
-        let is_recording = self.is_recording.clone();
-
-        // Build the stream
-        let stream = match sample_format {
-            cpal::SampleFormat::F32 => {
-                let app_data = app.clone();
-                let app_error = app.clone();
-
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        // Only process audio if we're recording
-                        if !*is_recording.lock().unwrap() {
-                            return;
-                        }
+        let frame = header.encode(&pcm16_to_bytes(chunk));
+        let streamed = self.queue.push(ProtocolMessage::Audio(frame));
+        let info = AudioChunkInfo {
+            sequence: header.sequence,
+            sample_offset: header.sample_offset,
+            samples: chunk.len(),
+            streamed,
+            queue_depth: self.queue.depth(),
+            dropped: self.queue.dropped(),
+        };
+        events.emit("audio_chunk", &info);
+    }
 
-                        let mut buffer = buffer.lock().unwrap();
-                        for &sample in data {
-                            buffer.push_back(sample);
-                        }
+    /// Queues a session control message. Returns false if there is no
+    /// connection to send it on.
+    fn send_control(&self, message: ProtocolMessage) -> bool {
+        let queued = self.queue.push(message);
+        if !queued {
+            println!("WebSocket not connected, session control message not sent");
+        }
+        queued
+    }
 
-                        // Keep buffer size reasonable (about 5 seconds of audio)
-                        let max_buffer_size = sample_rate as usize * channels as usize * 5;
-                        while buffer.len() > max_buffer_size {
-                            buffer.pop_front();
-                        }
+    /// Leaves a hole of `samples` in the session timeline. Returns the
+    /// session and the offset the hole starts at.
+    fn skip(&self, samples: u64) -> (Uuid, u64) {
+        let mut position = self.position.lock().unwrap();
+        let start = position.sample_offset;
+        position.sample_offset += samples;
+        (position.session_id, start)
+    }
+}
 
-                        // Stream audio data if buffer has enough samples (about 100ms worth)
-                        let chunk_size = sample_rate as usize * channels as usize / 10; // 100ms
-                        if buffer.len() >= chunk_size {
-                            // Extract a chunk of audio
-                            let mut chunk: Vec<f32> = Vec::with_capacity(chunk_size);
-                            for _ in 0..chunk_size.min(buffer.len()) {
-                                if let Some(sample) = buffer.pop_front() {
-                                    chunk.push(sample);
-                                }
-                            }
-
-                            // Convert to bytes and send to WebSocket
-                            let audio_bytes: Vec<u8> = chunk
-                                .iter()
-                                .flat_map(|&f| f.to_le_bytes().to_vec())
-                                .collect();
-
-                            // Emit audio chunk for streaming
-                            let _ = app_data.emit("audio_chunk", &audio_bytes);
-                        }
-                    },
-                    move |err| {
-                        eprintln!("Audio input error: {}", err);
-                        let _ = app_error.emit("audio_error", err.to_string());
-                    },
-                    None,
-                )
-            }
-            cpal::SampleFormat::I16 => {
-                let buffer = self.audio_buffer.clone();
-                let app = self.app.clone();
-                let is_recording = self.is_recording.clone();
-                let app_data = app.clone();
-                let app_error = app.clone();
-
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                        // Only process audio if we're recording
-                        if !*is_recording.lock().unwrap() {
-                            return;
-                        }
+/// What the UI hears about each streamed chunk; the audio itself goes
+/// straight to the WebSocket writer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioChunkInfo {
+    pub sequence: u64,
+    pub sample_offset: u64,
+    pub samples: usize,
+    /// False if the chunk was not queued for the backend: there was no
+    /// connection, or the queue was full.
+    pub streamed: bool,
+    pub queue_depth: usize,
+    pub dropped: u64,
+}
 
-                        let mut buffer = buffer.lock().unwrap();
-                        for &sample in data {
-                            buffer.push_back(sample as f32 / i16::MAX as f32);
-                        }
+/// Audio the input stream failed to deliver during a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioGap {
+    pub session_id: String,
+    pub sample_offset: u64,
+    pub missing_samples: u64,
+    pub missing_ms: u64,
+}
 
-                        // Keep buffer size reasonable
-                        let max_buffer_size = sample_rate as usize * channels as usize * 5;
-                        while buffer.len() > max_buffer_size {
-                            buffer.pop_front();
-                        }
+/// Input level of one chunk, for drawing a live meter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioLevel {
+    pub rms_db: f32,
+    pub peak_db: f32,
+    pub is_clipping: bool,
+    pub silence_detected: bool,
+}
 
-                        // Stream audio data if buffer has enough samples (about 100ms worth)
-                        let chunk_size = sample_rate as usize * channels as usize / 10; // 100ms
-                        if buffer.len() >= chunk_size {
-                            // Extract a chunk of audio
-                            let mut chunk: Vec<f32> = Vec::with_capacity(chunk_size);
-                            for _ in 0..chunk_size.min(buffer.len()) {
-                                if let Some(sample) = buffer.pop_front() {
-                                    chunk.push(sample);
-                                }
-                            }
-
-                            // Convert to bytes and send to WebSocket
-                            let audio_bytes: Vec<u8> = chunk
-                                .iter()
-                                .flat_map(|&f| f.to_le_bytes().to_vec())
-                                .collect();
-
-                            // Emit audio chunk for streaming
-                            let _ = app_data.emit("audio_chunk", &audio_bytes);
-                        }
-                    },
-                    move |err| {
-                        eprintln!("Audio input error: {}", err);
-                        let _ = app_error.emit("audio_error", err.to_string());
-                    },
-                    None,
-                )
-            }
-            cpal::SampleFormat::U16 => {
-                let buffer = self.audio_buffer.clone();
-                let app = self.app.clone();
-                let is_recording = self.is_recording.clone();
-                let app_data = app.clone();
-                let app_error = app.clone();
-
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                        // Only process audio if we're recording
-                        if !*is_recording.lock().unwrap() {
-                            return;
-                        }
+/// Measures chunk levels, rate-limited to one reading per interval.
+struct LevelMeter {
+    silence_threshold_db: f32,
+    interval: Duration,
+    last_emit: Option<Instant>,
+}
 
-                        let mut buffer = buffer.lock().unwrap();
-                        for &sample in data {
-                            buffer.push_back((sample as f32 / u16::MAX as f32) - 0.5);
-                        }
+impl LevelMeter {
+    /// Samples this close to full scale count as clipped.
+    const CLIP_LEVEL: i16 = i16::MAX - 64;
 
-                        // Keep buffer size reasonable
-                        let max_buffer_size = sample_rate as usize * channels as usize * 5;
-                        while buffer.len() > max_buffer_size {
-                            buffer.pop_front();
-                        }
+    fn new(silence_threshold_db: f32) -> Self {
+        Self {
+            silence_threshold_db,
+            interval: Duration::from_millis(100),
+            last_emit: None,
+        }
+    }
 
-                        // Stream audio data if buffer has enough samples (about 100ms worth)
-                        let chunk_size = sample_rate as usize * channels as usize / 10; // 100ms
-                        if buffer.len() >= chunk_size {
-                            // Extract a chunk of audio
-                            let mut chunk: Vec<f32> = Vec::with_capacity(chunk_size);
-                            for _ in 0..chunk_size.min(buffer.len()) {
-                                if let Some(sample) = buffer.pop_front() {
-                                    chunk.push(sample);
-                                }
-                            }
-
-                            // Convert to bytes and send to WebSocket
-                            let audio_bytes: Vec<u8> = chunk
-                                .iter()
-                                .flat_map(|&f| f.to_le_bytes().to_vec())
-                                .collect();
-
-                            // Emit audio chunk for streaming
-                            let _ = app_data.emit("audio_chunk", &audio_bytes);
-                        }
-                    },
-                    move |err| {
-                        eprintln!("Audio input error: {}", err);
-                        let _ = app_error.emit("audio_error", err.to_string());
-                    },
-                    None,
-                )
+    fn measure(&mut self, chunk: &[i16]) -> Option<AudioLevel> {
+        if chunk.is_empty()
+            || self
+                .last_emit
+                .is_some_and(|last| last.elapsed() < self.interval)
+        {
+            return None;
+        }
+        self.last_emit = Some(Instant::now());
+
+        let sum_squares: f64 = chunk
+            .iter()
+            .map(|&s| {
+                let normalized = s as f64 / i16::MAX as f64;
+                normalized * normalized
+            })
+            .sum();
+        let rms = (sum_squares / chunk.len() as f64).sqrt() as f32;
+        let peak = chunk.iter().map(|&s| s.unsigned_abs()).max().unwrap_or(0);
+        let rms_db = amplitude_to_db(rms);
+
+        Some(AudioLevel {
+            rms_db,
+            peak_db: amplitude_to_db(peak as f32 / i16::MAX as f32),
+            is_clipping: peak >= Self::CLIP_LEVEL.unsigned_abs(),
+            silence_detected: rms_db < self.silence_threshold_db,
+        })
+    }
+}
+
+/// Converts a linear amplitude to dBFS, floored at -100 dB.
+pub fn amplitude_to_db(amplitude: f32) -> f32 {
+    (20.0 * amplitude.max(1e-5).log10()).max(-100.0)
+}
+
+/// Streaming converter from interleaved device audio to mono PCM16 at the
+/// target rate: downmix, anti-alias filter when decimating, then linear
+/// interpolation.
+pub struct PcmConverter {
+    channels: usize,
+    step: f64,
+    position: f64,
+    previous: f32,
+    frame_sum: f32,
+    frame_fill: usize,
+    filter: Option<LowPassFilter>,
+}
+
+impl PcmConverter {
+    pub fn new(input_rate: u32, input_channels: u16, output_rate: u32) -> Self {
+        let step = input_rate as f64 / output_rate.max(1) as f64;
+        Self {
+            channels: input_channels.max(1) as usize,
+            step,
+            position: 1.0,
+            previous: 0.0,
+            frame_sum: 0.0,
+            frame_fill: 0,
+            filter: (step > 1.0).then(|| LowPassFilter::new(0.45 / step)),
+        }
+    }
+
+    pub fn process(&mut self, interleaved: impl IntoIterator<Item = f32>, output: &mut Vec<i16>) {
+        for sample in interleaved {
+            // Downmix one frame to mono
+            self.frame_sum += sample;
+            self.frame_fill += 1;
+            if self.frame_fill < self.channels {
+                continue;
+            }
+            let mut mono = self.frame_sum / self.channels as f32;
+            self.frame_sum = 0.0;
+            self.frame_fill = 0;
+
+            if let Some(filter) = self.filter.as_mut() {
+                mono = filter.process(mono);
             }
-            _ => {
-                return Err("Unsupported sample format".to_string());
+
+            // Emit every output sample that falls between the previous and this input sample
+            while self.position <= 1.0 {
+                let value = self.previous + (mono - self.previous) * self.position as f32;
+                output.push(f32_to_i16(value));
+                self.position += self.step;
             }
+            self.position -= 1.0;
+            self.previous = mono;
         }
-        .map_err(|e| format!("Failed to build input stream: {}", e))?;
+    }
+}
 
-        // Start the stream
-        stream
-            .play()
-            .map_err(|e| format!("Failed to start stream: {}", e))?;
+/// Hann-windowed sinc FIR low-pass, `cutoff` in cycles per input sample.
+struct LowPassFilter {
+    taps: Vec<f32>,
+    history: Vec<f32>,
+    index: usize,
+}
 
-        // Set recording flag
-        *recording = true;
+impl LowPassFilter {
+    const TAPS: usize = 31;
 
-        // Emit recording started event
-        let _ = self.app.emit("recording_started", ());
+    fn new(cutoff: f64) -> Self {
+        use std::f64::consts::PI;
 
-        println!("Recording started");
+        let middle = (Self::TAPS - 1) as f64 / 2.0;
+        let mut taps: Vec<f32> = (0..Self::TAPS)
+            .map(|i| {
+                let x = i as f64 - middle;
+                let sinc = if x == 0.0 {
+                    2.0 * cutoff
+                } else {
+                    (2.0 * PI * cutoff * x).sin() / (PI * x)
+                };
+                let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / (Self::TAPS - 1) as f64).cos();
+                (sinc * window) as f32
+            })
+            .collect();
 
-        // Keep the stream alive by forgetting it (for now)
-        // This is a simplified approach - in production, we'd need better stream management
-        std::mem::forget(stream);
+        // Normalize for unity gain at DC
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= sum);
 
-        Ok(())
+        Self {
+            taps,
+            history: vec![0.0; Self::TAPS],
+            index: 0,
+        }
     }
 
-    pub fn stop_recording(&self) -> Result<(), String> {
-        let mut recording = self.is_recording.lock().unwrap();
-        if !*recording {
-            return Ok(());
-        }
+    fn process(&mut self, sample: f32) -> f32 {
+        let len = self.history.len();
+        self.history[self.index] = sample;
+        let output = self
+            .taps
+            .iter()
+            .enumerate()
+            .map(|(i, tap)| tap * self.history[(self.index + len - i) % len])
+            .sum();
+        self.index = (self.index + 1) % len;
+        output
+    }
+}
 
-        // Set recording flag to false
-        *recording = false;
+fn f32_to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
 
-        // Get the recorded audio data
-        let audio_data = self.get_audio_data();
+pub fn pcm16_to_bytes(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
 
-        // Convert f32 to bytes for WebSocket transmission
-        let audio_bytes: Vec<u8> = audio_data
-            .iter()
-            .flat_map(|&f| f.to_le_bytes().to_vec())
-            .collect();
+/// Owns the (non-Send) cpal stream for the lifetime of the recorder. Dropping
+/// the stream is what releases the device and stops its callback.
+struct AudioWorker {
+    context: CaptureContext,
+    stream: Option<CaptureStreams>,
+    /// The config changed mid-recording; reopen the warm stream once it ends.
+    stale: bool,
+    mic_indicator: bool,
+}
 
-        // Emit recording stopped event with audio data
-        let _ = self.app.emit("recording_stopped", &audio_bytes);
+impl AudioWorker {
+    fn new(context: CaptureContext) -> Self {
+        Self {
+            context,
+            stream: None,
+            stale: false,
+            mic_indicator: false,
+        }
+    }
 
-        // Send audio data to WebSocket if available
-        if !audio_bytes.is_empty() {
-            let _ = self.app.emit("send_audio_to_websocket", &audio_bytes);
+    fn run(mut self, commands: Receiver<AudioCommand>) {
+        while let Ok(command) = commands.recv() {
+            match command {
+                AudioCommand::Start { reply } => {
+                    if self.stale {
+                        self.close();
+                    }
+                    let result = match &self.stream {
+                        Some(streams) => Ok(streams.device_name.clone()),
+                        None => self.open().map(|_| self.device_name()),
+                    };
+                    // The stream now belongs to the recording
+                    self.set_mic_indicator(false);
+                    let _ = reply.send(result);
+                }
+                AudioCommand::Stop { reply } => {
+                    if !self.warm_mic() || self.stale {
+                        self.close();
+                    } else if let Some(streams) = &self.stream {
+                        streams.sink.lock().unwrap().finish_session();
+                    }
+                    if let Err(e) = self.warm_up() {
+                        eprintln!("Failed to keep microphone warm: {}", e);
+                    }
+                    self.update_mic_indicator();
+                    let _ = reply.send(());
+                }
+                AudioCommand::SwitchDevice { reply } => {
+                    let result = if self.stream.is_some() {
+                        self.close();
+                        self.open().map(|_| Some(self.device_name()))
+                    } else {
+                        Ok(None)
+                    };
+                    self.update_mic_indicator();
+                    let _ = reply.send(result);
+                }
+                AudioCommand::Refresh { reply } => {
+                    let result = if self.is_recording() {
+                        self.stale = true;
+                        Ok(())
+                    } else {
+                        self.close();
+                        self.warm_up()
+                    };
+                    self.update_mic_indicator();
+                    let _ = reply.send(result);
+                }
+                AudioCommand::CheckDevice { reply } => {
+                    let lost = self
+                        .stream
+                        .as_ref()
+                        .map(|streams| streams.device_name.clone())
+                        .filter(|name| !self.context.source.is_available(name));
+                    let result = match lost {
+                        Some(lost) => {
+                            self.close();
+                            self.open().map(|_| Some((lost, self.device_name())))
+                        }
+                        None => Ok(None),
+                    };
+                    self.update_mic_indicator();
+                    let _ = reply.send(result);
+                }
+                AudioCommand::Test { duration, reply } => {
+                    let result = if self.is_recording() {
+                        Err("Stop recording before testing the microphone".to_string())
+                    } else {
+                        // Free the device from the warm stream while testing
+                        self.close();
+                        let result = self.context.test_microphone(duration);
+                        if let Err(e) = self.warm_up() {
+                            eprintln!("Failed to keep microphone warm: {}", e);
+                        }
+                        result
+                    };
+                    self.update_mic_indicator();
+                    let _ = reply.send(result);
+                }
+            }
         }
+    }
 
-        println!("Recording stopped, {} bytes captured", audio_bytes.len());
+    /// Opens the idle stream that feeds the pre-roll if warm mic is enabled.
+    fn warm_up(&mut self) -> Result<(), String> {
+        if self.warm_mic() && self.stream.is_none() {
+            self.open()?;
+            println!("Warm mic open on '{}'", self.device_name());
+        }
+        Ok(())
+    }
 
+    fn open(&mut self) -> Result<(), String> {
+        self.stream = Some(self.context.open_stream()?);
+        self.stale = false;
         Ok(())
     }
 
-    pub fn is_recording(&self) -> bool {
-        *self.is_recording.lock().unwrap()
+    fn close(&mut self) {
+        if let Some(streams) = self.stream.take() {
+            for stream in std::iter::once(&streams.microphone).chain(streams.loopback.iter()) {
+                if let Err(e) = stream.pause() {
+                    eprintln!("Failed to pause audio stream: {}", e);
+                }
+            }
+            streams.sink.lock().unwrap().finish_session();
+            drop(streams);
+        }
     }
 
-    pub fn select_device(&self, device_name: &str) -> Result<(), String> {
-        let mut selected = self.selected_device.lock().unwrap();
-        *selected = Some(device_name.to_string());
-        Ok(())
+    fn device_name(&self) -> String {
+        self.stream
+            .as_ref()
+            .map(|streams| streams.device_name.clone())
+            .unwrap_or_default()
+    }
+
+    fn warm_mic(&self) -> bool {
+        self.context.config.lock().unwrap().warm_mic
+    }
+
+    fn is_recording(&self) -> bool {
+        *self.context.is_recording.lock().unwrap()
+    }
+
+    /// Shows in the tray whenever the mic is open without a recording running.
+    fn update_mic_indicator(&mut self) {
+        self.set_mic_indicator(self.stream.is_some() && !self.is_recording());
     }
 
-    pub fn get_audio_data(&self) -> Vec<f32> {
-        let mut buffer = self.audio_buffer.lock().unwrap();
-        let data: Vec<f32> = buffer.drain(..).collect();
-        data
+    fn set_mic_indicator(&mut self, warm: bool) {
+        if warm != self.mic_indicator {
+            self.mic_indicator = warm;
+            self.context.events.warm_mic_changed(warm);
+        }
     }
 }
 
@@ -356,6 +1818,11 @@ pub fn select_audio_device(
     state.inner().select_device(&device_name)
 }
 
+#[tauri::command]
+pub fn get_active_audio_device(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Option<String> {
+    state.inner().active_device()
+}
+
 #[tauri::command]
 pub fn start_recording(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Result<(), String> {
     state.inner().start_recording()
@@ -366,12 +1833,378 @@ pub fn stop_recording(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Result<(
     state.inner().stop_recording()
 }
 
+#[tauri::command]
+pub fn pause_recording(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Result<(), String> {
+    state.inner().pause_recording()
+}
+
+#[tauri::command]
+pub fn resume_recording(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Result<(), String> {
+    state.inner().resume_recording()
+}
+
 #[tauri::command]
 pub fn is_recording(state: tauri::State<Arc<SimpleAudioRecorder>>) -> bool {
     state.inner().is_recording()
 }
 
 #[tauri::command]
-pub fn get_audio_data(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Vec<f32> {
+pub fn get_audio_data(state: tauri::State<Arc<SimpleAudioRecorder>>) -> Vec<i16> {
     state.inner().get_audio_data()
 }
+
+/// Records a few seconds from the selected microphone and returns a quality
+/// report. Runs off the main thread since it waits for the recording.
+#[tauri::command]
+pub async fn run_microphone_test(
+    duration_secs: Option<u64>,
+    state: tauri::State<'_, Arc<SimpleAudioRecorder>>,
+) -> Result<MicTestReport, String> {
+    let recorder = state.inner().clone();
+    let duration = Duration::from_secs(duration_secs.unwrap_or(3).clamp(1, 10));
+    tauri::async_runtime::spawn_blocking(move || recorder.test_microphone(duration))
+        .await
+        .map_err(|e| format!("Microphone test failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn get_audio_format(state: tauri::State<Arc<SimpleAudioRecorder>>) -> AudioFormat {
+    state.inner().output_format()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_source::{
+        ErrorCallback, FileSource, SampleCallback, SourceFormat, ToneSource,
+    };
+    use crate::chunk_queue::OutboundQueueConfig;
+
+    /// Keeps the names of the events the recorder emitted.
+    #[derive(Default)]
+    struct CollectedEvents {
+        names: Mutex<Vec<String>>,
+    }
+
+    impl CollectedEvents {
+        fn count(&self, event: &str) -> usize {
+            self.names
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|name| *name == event)
+                .count()
+        }
+    }
+
+    impl RecorderEvents for CollectedEvents {
+        fn emit_event(&self, event: &str, _payload: serde_json::Value) {
+            self.names.lock().unwrap().push(event.to_string());
+        }
+
+        fn audio_error(&self, message: &str, _details: Option<&str>) {
+            panic!("unexpected audio error: {}", message);
+        }
+
+        fn warm_mic_changed(&self, _warm: bool) {}
+
+        fn request_stop(&self, reason: &'static str) {
+            panic!("unexpected automatic stop: {}", reason);
+        }
+
+        fn archive_recording(
+            &self,
+            _config: ArchiveConfig,
+            _session_id: Uuid,
+            _samples: Vec<i16>,
+            _format: AudioFormat,
+        ) {
+        }
+    }
+
+    /// Delivers 16 kHz mono blocks stamped with the given capture times,
+    /// counted from the start of the stream.
+    struct ScriptedSource {
+        blocks: Vec<(Duration, Vec<f32>)>,
+    }
+
+    struct FinishedStream;
+
+    impl SourceStream for FinishedStream {
+        fn pause(&self) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    impl AudioSource for ScriptedSource {
+        fn prepare(&mut self, _device: Option<&str>) -> Result<SourceFormat, String> {
+            Ok(SourceFormat {
+                device_name: "scripted".to_string(),
+                sample_rate: 16000,
+                channels: 1,
+                fallback_from: None,
+            })
+        }
+
+        fn start(
+            &mut self,
+            mut on_data: SampleCallback,
+            _on_error: ErrorCallback,
+        ) -> Result<Box<dyn SourceStream>, String> {
+            let blocks = std::mem::take(&mut self.blocks);
+            let started = Instant::now();
+            std::thread::spawn(move || {
+                for (offset, block) in blocks {
+                    on_data(&block, started + offset);
+                }
+            });
+            Ok(Box::new(FinishedStream))
+        }
+    }
+
+    /// Records from `source` until `expected` samples are kept, then stops.
+    fn record(
+        source: Box<dyn AudioSource>,
+        expected: usize,
+        config: AudioConfig,
+    ) -> (Vec<i16>, Arc<CollectedEvents>) {
+        let events = Arc::new(CollectedEvents::default());
+        let recorder = SimpleAudioRecorder::with_events(
+            events.clone(),
+            source,
+            Box::new(ToneSource::new(440.0)),
+            AudioChunkQueue::new(OutboundQueueConfig::default(), None),
+            CueGate::default(),
+            SessionTracker::default(),
+        );
+        recorder.update_config(config).unwrap();
+
+        recorder.start_recording().unwrap();
+        assert!(recorder.is_recording());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while recorder.session.len() < expected && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // Let the source run dry, so anything still missing is held back
+        // rather than not yet delivered
+        std::thread::sleep(Duration::from_millis(50));
+        recorder.stop_recording().unwrap();
+
+        assert!(!recorder.is_recording());
+        (recorder.get_audio_data(), events)
+    }
+
+    /// Records a 16 kHz mono file from start to stop with `config`.
+    fn record_file(samples: &[i16], config: AudioConfig) -> (Vec<i16>, Arc<CollectedEvents>) {
+        let path = std::env::temp_dir().join(format!("gorlami-recorder-{}.pcm", Uuid::new_v4()));
+        std::fs::write(&path, pcm16_to_bytes(samples)).unwrap();
+        let result = record(
+            Box::new(FileSource::raw_pcm16(&path, 16000, 1)),
+            samples.len(),
+            config,
+        );
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// 250ms of a sawtooth at the output rate, so nothing is resampled.
+    fn sawtooth() -> Vec<i16> {
+        (0..4000).map(|i| (i % 200 - 100) * 200).collect()
+    }
+
+    /// One second of a stereo 48 kHz signal, `left` and `right` given per frame.
+    fn stereo_48k(left: impl Fn(f32) -> f32, right: impl Fn(f32) -> f32) -> Vec<f32> {
+        (0..48000)
+            .flat_map(|i| {
+                let t = i as f32 / 48000.0;
+                [left(t), right(t)]
+            })
+            .collect()
+    }
+
+    fn tone(frequency: f32, amplitude: f32) -> impl Fn(f32) -> f32 {
+        move |t| amplitude * (std::f32::consts::TAU * frequency * t).sin()
+    }
+
+    fn convert(input: &[f32]) -> Vec<i16> {
+        let mut output = Vec::new();
+        PcmConverter::new(48000, 2, 16000).process(input.iter().copied(), &mut output);
+        output
+    }
+
+    /// RMS past the filter's start-up, as a fraction of full scale.
+    fn rms(samples: &[i16]) -> f32 {
+        let settled = &samples[100..];
+        let energy: f64 = settled.iter().map(|&s| (s as f64).powi(2)).sum();
+        ((energy / settled.len() as f64).sqrt() / 32767.0) as f32
+    }
+
+    #[test]
+    fn converter_downmixes_48k_stereo_to_16k_mono() {
+        let output = convert(&stereo_48k(|_| 0.5, |_| -0.1));
+        assert_eq!(output.len(), 16000);
+        // The mean of both channels, once the filter has filled
+        let expected = (0.2 * 32767.0) as i16;
+        assert!(output[20..].iter().all(|&s| (s - expected).abs() <= 2));
+
+        // Opposite channels cancel out
+        let output = convert(&stereo_48k(tone(440.0, 0.5), tone(440.0, -0.5)));
+        assert!(output.iter().all(|&s| s.abs() <= 1));
+    }
+
+    #[test]
+    fn converter_filters_out_tones_above_8k() {
+        let speech = rms(&convert(&stereo_48k(tone(1000.0, 0.5), tone(1000.0, 0.5))));
+        let high = rms(&convert(&stereo_48k(
+            tone(12000.0, 0.5),
+            tone(12000.0, 0.5),
+        )));
+
+        assert!((speech - 0.5 / 2f32.sqrt()).abs() < 0.02, "{}", speech);
+        assert!(high < speech / 50.0, "{} vs {}", high, speech);
+    }
+
+    #[test]
+    fn converter_carries_partial_frames_between_pushes() {
+        let input = stereo_48k(tone(440.0, 0.5), tone(660.0, 0.3));
+        let whole = convert(&input);
+
+        // Blocks that split frames and resampling steps alike
+        let mut converter = PcmConverter::new(48000, 2, 16000);
+        let mut pieces = Vec::new();
+        for block in input.chunks(7) {
+            converter.process(block.iter().copied(), &mut pieces);
+        }
+        assert_eq!(pieces, whole);
+    }
+
+    #[test]
+    fn overlapping_starts_begin_one_session() {
+        let events = Arc::new(CollectedEvents::default());
+        let recorder = Arc::new(SimpleAudioRecorder::with_events(
+            events.clone(),
+            Box::new(ToneSource::new(440.0)),
+            Box::new(ToneSource::new(440.0)),
+            AudioChunkQueue::new(OutboundQueueConfig::default(), None),
+            CueGate::default(),
+            SessionTracker::default(),
+        ));
+
+        let starts: Vec<_> = (0..8)
+            .map(|_| {
+                let recorder = recorder.clone();
+                std::thread::spawn(move || recorder.start_recording().is_ok())
+            })
+            .collect();
+        let started = starts
+            .into_iter()
+            .map(|start| start.join().unwrap())
+            .filter(|&started| started)
+            .count();
+
+        assert_eq!(started, 1);
+        assert_eq!(events.count("recording_started"), 1);
+        recorder.stop_recording().unwrap();
+    }
+
+    #[test]
+    fn records_a_file_source_from_start_to_stop() {
+        let samples = sawtooth();
+        let (captured, events) = record_file(&samples, AudioConfig::default());
+
+        assert_eq!(captured.len(), samples.len());
+        for (&actual, &expected) in captured.iter().zip(&samples) {
+            assert!((actual - expected).abs() <= 1, "{} != {}", actual, expected);
+        }
+
+        // 100ms chunks and the partial tail
+        assert_eq!(events.count("audio_chunk"), 3);
+        assert_eq!(events.count("recording_started"), 1);
+        assert_eq!(events.count("recording_stopped"), 1);
+    }
+
+    #[test]
+    fn stopping_flushes_audio_held_by_the_dsp_chain() {
+        let samples = sawtooth();
+        let config = AudioConfig {
+            dsp: DspConfig {
+                noise_suppression_enabled: true,
+                ..DspConfig::default()
+            },
+            ..AudioConfig::default()
+        };
+        let (captured, _) = record_file(&samples, config);
+
+        assert_eq!(captured.len(), samples.len());
+    }
+
+    #[test]
+    fn lost_input_is_kept_as_silence() {
+        // 100ms blocks, with the one captured at 200ms missing
+        let block = vec![0.25f32; 1600];
+        let blocks = [0, 100, 300]
+            .into_iter()
+            .map(|ms| (Duration::from_millis(ms), block.clone()))
+            .collect();
+        let (captured, events) = record(
+            Box::new(ScriptedSource { blocks }),
+            6400,
+            AudioConfig::default(),
+        );
+
+        assert_eq!(captured.len(), 6400);
+        assert!(captured[..3200].iter().all(|&s| s > 8000));
+        assert!(captured[3200..4800].iter().all(|&s| s == 0));
+        assert!(captured[4800..].iter().all(|&s| s > 8000));
+        assert_eq!(events.count("audio_gap"), 1);
+    }
+
+    #[test]
+    fn gap_detector_follows_capture_timestamps() {
+        let mut gaps = GapDetector::new(16000);
+        let started = Instant::now();
+        let ms = |ms| started + Duration::from_millis(ms);
+
+        // Contiguous 10ms blocks, whenever the callbacks happen to run
+        for block in 0..10 {
+            assert_eq!(gaps.check(ms(10 * block), 160), None);
+        }
+
+        // Timestamp jitter is not lost audio
+        assert_eq!(gaps.check(ms(130), 160), None);
+        assert_eq!(gaps.check(ms(110), 160), None);
+
+        // 120ms of blocks never arrived
+        assert_eq!(gaps.check(ms(240), 160), Some(1920));
+        assert_eq!(gaps.check(ms(250), 160), None);
+
+        // A new session starts its own timeline
+        gaps.reset();
+        assert_eq!(gaps.check(ms(5000), 160), None);
+        assert_eq!(gaps.check(ms(5010), 160), None);
+    }
+
+    #[test]
+    fn cues_are_gated_unless_speech_may_overlap_them() {
+        // (cue, pre-roll, recording, gated)
+        let cases = [
+            (Cue::Start, false, true, true),
+            (Cue::Start, true, true, false),
+            (Cue::Stop, true, false, true),
+            (Cue::Paste, false, false, true),
+            (Cue::Error, true, false, true),
+            (Cue::Error, false, true, false),
+            (Cue::Error, true, true, false),
+        ];
+        for (cue, pre_roll, recording, gated) in cases {
+            assert_eq!(
+                cue_is_gated(cue, pre_roll, recording),
+                gated,
+                "{:?} with pre-roll {} while recording {}",
+                cue,
+                pre_roll,
+                recording
+            );
+        }
+    }
+}
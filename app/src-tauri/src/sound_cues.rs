@@ -0,0 +1,403 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Audible feedback for recording events, for when the overlay is hidden.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundCueConfig {
+    pub enabled: bool,
+    /// Output device name, `None` meaning the default one.
+    pub output_device: Option<String>,
+    /// Playback volume from 0.0 to 1.0.
+    pub volume: f32,
+}
+
+impl Default for SoundCueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            output_device: None,
+            volume: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    Start,
+    Stop,
+    Error,
+    Paste,
+}
+
+/// App events that play a cue.
+pub const CUE_EVENTS: &[(&str, Cue)] = &[
+    ("recording_started", Cue::Start),
+    ("recording_stopped", Cue::Stop),
+    ("recording_cancelled", Cue::Stop),
+    ("app_error", Cue::Error),
+    ("text_pasted", Cue::Paste),
+];
+
+impl Cue {
+    /// Frequency (0 for a rest) and length in milliseconds of each note.
+    fn notes(self) -> &'static [(f32, u64)] {
+        match self {
+            Cue::Start => &[(660.0, 70), (880.0, 90)],
+            Cue::Stop => &[(880.0, 70), (660.0, 90)],
+            Cue::Error => &[(330.0, 120), (0.0, 60), (330.0, 120)],
+            Cue::Paste => &[(1320.0, 60)],
+        }
+    }
+
+    fn duration(self) -> Duration {
+        Duration::from_millis(self.notes().iter().map(|&(_, ms)| ms).sum())
+    }
+}
+
+/// Tells the capture path when each cue was audible, so the microphone does
+/// not pick it up.
+#[derive(Clone, Default)]
+pub struct CueGate {
+    playing: Arc<Mutex<Vec<CuePlayback>>>,
+}
+
+/// When a cue came out of the speakers, tail included.
+#[derive(Debug, Clone, Copy)]
+struct CuePlayback {
+    cue: Cue,
+    start: Instant,
+    end: Instant,
+}
+
+impl CueGate {
+    /// Room echo and resampler delay after a cue ends.
+    const TAIL: Duration = Duration::from_millis(100);
+    /// How long a finished cue is kept for input blocks that arrive late.
+    const HISTORY: Duration = Duration::from_secs(2);
+
+    /// Notes that `cue` becomes audible at `start`.
+    fn mark_playing(&self, cue: Cue, start: Instant) {
+        let now = Instant::now();
+        let mut playing = self.playing.lock().unwrap();
+        playing.retain(|playback| playback.end + Self::HISTORY > now);
+        playing.push(CuePlayback {
+            cue,
+            start,
+            end: start + cue.duration() + Self::TAIL,
+        });
+    }
+
+    /// Zeroes the frames of `samples` that overlap the playback of a cue
+    /// `gated` accepts. The first frame was captured at `captured_at`.
+    pub fn mute(
+        &self,
+        captured_at: Instant,
+        sample_rate: u32,
+        channels: u16,
+        samples: &mut [i16],
+        gated: impl Fn(Cue) -> bool,
+    ) {
+        let playing = self.playing.lock().unwrap();
+        let channels = channels.max(1) as usize;
+        let frames = samples.len() / channels;
+        let frame_at = |at: Instant| {
+            let offset = at.saturating_duration_since(captured_at).as_secs_f64();
+            (offset * sample_rate as f64).round() as usize
+        };
+
+        for playback in playing.iter().filter(|playback| gated(playback.cue)) {
+            let first = frame_at(playback.start).min(frames);
+            let last = (frame_at(playback.end) + 1).min(frames);
+            if playback.end > captured_at && first < last {
+                samples[first * channels..last * channels].fill(0);
+            }
+        }
+    }
+}
+
+/// Plays cues on an output device from a thread of its own, since cpal
+/// streams cannot be moved between threads.
+pub struct SoundCuePlayer {
+    config: Arc<Mutex<SoundCueConfig>>,
+    gate: CueGate,
+    last_error_cue: Mutex<Option<Instant>>,
+    cues: Sender<Cue>,
+}
+
+impl SoundCuePlayer {
+    /// Error cues closer together than this are skipped.
+    const ERROR_CUE_INTERVAL: Duration = Duration::from_secs(2);
+
+    pub fn new(config: SoundCueConfig) -> Self {
+        let config = Arc::new(Mutex::new(config));
+        let gate = CueGate::default();
+
+        let (cues, receiver) = channel();
+        let player_config = config.clone();
+        let player_gate = gate.clone();
+        std::thread::Builder::new()
+            .name("sound-cues".to_string())
+            .spawn(move || run_player(receiver, player_config, player_gate))
+            .expect("Failed to spawn sound cue thread");
+
+        Self {
+            config,
+            gate,
+            last_error_cue: Mutex::new(None),
+            cues,
+        }
+    }
+
+    /// Shared with the recorder, which drops input captured while a cue
+    /// plays.
+    pub fn gate(&self) -> CueGate {
+        self.gate.clone()
+    }
+
+    pub fn update_config(&self, config: SoundCueConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Output device chosen for cues, also used to play back recordings.
+    pub fn output_device_name(&self) -> Option<String> {
+        self.config.lock().unwrap().output_device.clone()
+    }
+
+    pub fn play(&self, cue: Cue) {
+        if !self.config.lock().unwrap().enabled {
+            return;
+        }
+
+        // One error often causes several in a row; beep once
+        if cue == Cue::Error {
+            let mut last = self.last_error_cue.lock().unwrap();
+            let now = Instant::now();
+            if last.is_some_and(|last| now.duration_since(last) < Self::ERROR_CUE_INTERVAL) {
+                return;
+            }
+            *last = Some(now);
+        }
+
+        let _ = self.cues.send(cue);
+    }
+}
+
+fn run_player(cues: Receiver<Cue>, config: Arc<Mutex<SoundCueConfig>>, gate: CueGate) {
+    while let Ok(cue) = cues.recv() {
+        let config = config.lock().unwrap().clone();
+        if !config.enabled {
+            continue;
+        }
+
+        match open_output(&config, cue, &gate) {
+            Ok(stream) => {
+                if let Err(e) = stream.play() {
+                    eprintln!("Failed to play sound cue: {}", e);
+                    continue;
+                }
+                // Keep the stream alive until the cue has played
+                std::thread::sleep(cue.duration() + Duration::from_millis(50));
+            }
+            Err(e) => eprintln!("Failed to open sound cue output: {}", e),
+        }
+    }
+}
+
+/// The output device called `name`, or the default one if it is missing.
+pub fn output_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    let mut device = None;
+    if let Some(name) = name {
+        device = host
+            .output_devices()
+            .map_err(|e| format!("Failed to enumerate output devices: {}", e))?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false));
+    }
+    match device {
+        Some(device) => Ok(device),
+        None => host
+            .default_output_device()
+            .ok_or_else(|| "No default output device found".to_string()),
+    }
+}
+
+/// Builds a paused output stream that plays `cue` once, then silence, and
+/// tells `gate` when the cue is heard.
+fn open_output(config: &SoundCueConfig, cue: Cue, gate: &CueGate) -> Result<cpal::Stream, String> {
+    let device = output_device(config.output_device.as_deref())?;
+    let output_config = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get default output config: {}", e))?;
+    let sample_format = output_config.sample_format();
+    let stream_config: cpal::StreamConfig = output_config.into();
+    let samples = synthesize(
+        cue,
+        stream_config.sample_rate.0,
+        stream_config.channels,
+        config.volume.clamp(0.0, 1.0),
+    );
+    let gate = gate.clone();
+    let started = move |start| gate.mark_playing(cue, start);
+
+    match sample_format {
+        cpal::SampleFormat::I8 => {
+            build_output_stream::<i8>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::I16 => {
+            build_output_stream::<i16>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::I32 => {
+            build_output_stream::<i32>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::U8 => {
+            build_output_stream::<u8>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::U16 => {
+            build_output_stream::<u16>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::U32 => {
+            build_output_stream::<u32>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::F32 => {
+            build_output_stream::<f32>(&device, &stream_config, samples, started)
+        }
+        cpal::SampleFormat::F64 => {
+            build_output_stream::<f64>(&device, &stream_config, samples, started)
+        }
+        other => return Err(format!("Unsupported sample format: {}", other)),
+    }
+    .map_err(|e| format!("Failed to build output stream: {}", e))
+}
+
+fn build_output_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    samples: Vec<f32>,
+    started: impl FnOnce(Instant) + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let mut position = 0;
+    let mut started = Some(started);
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+            // The first buffer is heard once the output latency has passed
+            if let Some(started) = started.take() {
+                let timestamp = info.timestamp();
+                let latency = timestamp
+                    .playback
+                    .duration_since(&timestamp.callback)
+                    .unwrap_or_default();
+                started(Instant::now() + latency);
+            }
+            for sample in data.iter_mut() {
+                let value = samples.get(position).copied().unwrap_or(0.0);
+                *sample = T::from_sample(value);
+                position += 1;
+            }
+        },
+        |err| eprintln!("Sound cue output error: {}", err),
+        None,
+    )
+}
+
+/// Renders the notes of a cue as interleaved samples, with short fades so
+/// they do not click.
+fn synthesize(cue: Cue, sample_rate: u32, channels: u16, volume: f32) -> Vec<f32> {
+    let fade = (sample_rate as usize / 200).max(1); // 5ms
+    let mut samples = Vec::new();
+
+    for &(frequency, ms) in cue.notes() {
+        let len = (sample_rate as u64 * ms / 1000) as usize;
+        for i in 0..len {
+            let envelope = (i.min(len - 1 - i) as f32 / fade as f32).min(1.0);
+            let value = if frequency > 0.0 {
+                (TAU * frequency * i as f32 / sample_rate as f32).sin() * envelope * volume
+            } else {
+                0.0
+            };
+            samples.extend(std::iter::repeat_n(value, channels as usize));
+        }
+    }
+
+    samples
+}
+
+#[tauri::command]
+pub fn get_output_devices() -> Result<Vec<String>, String> {
+    let host = cpal::default_host();
+    let devices = host
+        .output_devices()
+        .map_err(|e| format!("Failed to enumerate output devices: {}", e))?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 frames of stereo input captured from `at`, at 1 kHz.
+    fn gated_block(gate: &CueGate, at: Instant, gated: impl Fn(Cue) -> bool) -> Vec<i16> {
+        let mut samples = vec![1i16; 200];
+        gate.mute(at, 1000, 2, &mut samples, gated);
+        samples
+    }
+
+    #[test]
+    fn mutes_only_input_captured_while_a_cue_plays() {
+        let gate = CueGate::default();
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+
+        // The paste cue plays for 60ms, heard from 30ms into the block
+        gate.mark_playing(Cue::Paste, now + ms(30));
+        let samples = gated_block(&gate, now, |_| true);
+        assert!(samples[..60].iter().all(|&s| s == 1));
+        assert!(samples[60..].iter().all(|&s| s == 0));
+
+        // Muted until the tail ends at 190ms
+        let samples = gated_block(&gate, now + ms(100), |_| true);
+        assert!(samples[..182].iter().all(|&s| s == 0));
+        assert!(samples[182..].iter().all(|&s| s == 1));
+
+        // Input from before and after the cue is untouched
+        assert!(gated_block(&gate, now - ms(100), |_| true)
+            .iter()
+            .all(|&s| s == 1));
+        assert!(gated_block(&gate, now + ms(200), |_| true)
+            .iter()
+            .all(|&s| s == 1));
+    }
+
+    #[test]
+    fn leaves_cues_the_caller_keeps() {
+        let gate = CueGate::default();
+        let now = Instant::now();
+        gate.mark_playing(Cue::Start, now);
+        gate.mark_playing(Cue::Error, now);
+
+        let samples = gated_block(&gate, now, |cue| cue == Cue::Stop);
+        assert!(samples.iter().all(|&s| s == 1));
+        let samples = gated_block(&gate, now, |cue| cue == Cue::Error);
+        assert!(samples.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn forgets_cues_long_past() {
+        let gate = CueGate::default();
+        let long_ago = Instant::now() - Duration::from_secs(10);
+        gate.mark_playing(Cue::Paste, long_ago);
+        gate.mark_playing(Cue::Stop, Instant::now());
+
+        let playing = gate.playing.lock().unwrap();
+        assert_eq!(playing.len(), 1);
+        assert_eq!(playing[0].cue, Cue::Stop);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::simple_audio::get_audio_devices;
+use crate::simple_audio::{get_audio_devices, AudioDevice};
 use tauri::{
     menu::{Menu, MenuItem, PredefinedMenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
@@ -7,12 +7,72 @@ use tauri::{
 };
 
 pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
+    let devices = get_audio_devices().unwrap_or_default();
+    let menu = build_tray_menu(app, &devices)?;
+
+    let _tray = TrayIconBuilder::with_id("main")
+        .icon(app.default_window_icon().unwrap().clone())
+        .menu(&menu)
+        .show_menu_on_left_click(true)
+        .on_menu_event(move |app, event| match event.id.as_ref() {
+            "quit" => {
+                app.exit(0);
+            }
+            "dashboard" => {
+                open_main_window(app, None);
+            }
+            "settings" => {
+                open_main_window(app, Some("settings"));
+            }
+            "play_last" => {
+                if let Err(e) = crate::playback::play_last_recording_on(app, 0.0) {
+                    eprintln!("Failed to play last recording: {}", e);
+                }
+            }
+            id if id.starts_with("mic_") => {
+                // Handle microphone selection
+                let device_name = id.strip_prefix("mic_").unwrap_or("Default");
+                if let Some(audio_recorder) =
+                    app.try_state::<std::sync::Arc<crate::simple_audio::SimpleAudioRecorder>>()
+                {
+                    if let Err(e) = audio_recorder.select_device(device_name) {
+                        eprintln!("Failed to select audio device: {}", e);
+                    }
+                }
+            }
+            _ => {}
+        })
+        .on_tray_icon_event(|_tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                // Menu will show automatically due to show_menu_on_left_click(true)
+            }
+        })
+        .build(app)?;
+
+    Ok(())
+}
+
+fn build_tray_menu<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    devices: &[AudioDevice],
+) -> tauri::Result<Menu<R>> {
     let quit_item = MenuItem::with_id(app, "quit", "Quit Gorlami", true, None::<&str>)?;
     let settings_item = MenuItem::with_id(app, "settings", "Settings...", true, None::<&str>)?;
     let dashboard_item = MenuItem::with_id(app, "dashboard", "Dashboard", true, None::<&str>)?;
+    let play_last_item = MenuItem::with_id(
+        app,
+        "play_last",
+        "Play Last Recording",
+        true,
+        None::<&str>,
+    )?;
 
     // Create microphone submenu with real devices
-    let devices = get_audio_devices().unwrap_or_default();
     let mut mic_items = Vec::new();
 
     for device in devices {
@@ -54,57 +114,29 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
 
     let separator = PredefinedMenuItem::separator(app)?;
 
-    let menu = Menu::with_items(
+    Menu::with_items(
         app,
         &[
             &status_item,
             &separator,
             &dashboard_item,
             &microphone_menu,
+            &play_last_item,
             &settings_item,
             &separator,
             &quit_item,
         ],
-    )?;
+    )
+}
 
-    let _tray = TrayIconBuilder::with_id("main")
-        .icon(app.default_window_icon().unwrap().clone())
-        .menu(&menu)
-        .show_menu_on_left_click(true)
-        .on_menu_event(move |app, event| match event.id.as_ref() {
-            "quit" => {
-                app.exit(0);
-            }
-            "dashboard" => {
-                open_main_window(app, None);
-            }
-            "settings" => {
-                open_main_window(app, Some("settings"));
-            }
-            id if id.starts_with("mic_") => {
-                // Handle microphone selection
-                let device_name = id.strip_prefix("mic_").unwrap_or("Default");
-                if let Some(audio_recorder) =
-                    app.try_state::<std::sync::Arc<crate::simple_audio::SimpleAudioRecorder>>()
-                {
-                    if let Err(e) = audio_recorder.select_device(device_name) {
-                        eprintln!("Failed to select audio device: {}", e);
-                    }
-                }
-            }
-            _ => {}
-        })
-        .on_tray_icon_event(|_tray, event| {
-            if let TrayIconEvent::Click {
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
-                ..
-            } = event
-            {
-                // Menu will show automatically due to show_menu_on_left_click(true)
-            }
-        })
-        .build(app)?;
+/// Rebuilds the tray menu so the Microphone submenu lists `devices`.
+pub fn refresh_microphone_menu<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    devices: &[AudioDevice],
+) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id("main") {
+        tray.set_menu(Some(build_tray_menu(app, devices)?))?;
+    }
 
     Ok(())
 }
@@ -171,3 +203,22 @@ pub fn update_tray_status<R: Runtime>(
 
     Ok(())
 }
+
+/// Privacy indicator for the warm mic: the device stays open between
+/// recordings, so make that visible in the tray.
+pub fn set_warm_mic_indicator<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    warm: bool,
+) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id("main") {
+        if warm {
+            tray.set_tooltip(Some("Gorlami • Microphone is open (warm mic)"))?;
+            tray.set_title(Some("● Mic"))?;
+        } else {
+            tray.set_tooltip(Some("Gorlami"))?;
+            tray.set_title(None::<&str>)?;
+        }
+    }
+
+    Ok(())
+}
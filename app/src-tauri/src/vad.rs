@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+/// Energy-based voice activity detection settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VadConfig {
+    /// Stop recording automatically once speech is followed by silence.
+    pub enabled: bool,
+    /// Frames louder than this (dBFS) count as speech.
+    pub speech_threshold_db: f32,
+    /// Trailing silence after speech before recording stops (milliseconds).
+    pub silence_timeout_ms: u64,
+    /// Minimum continuous speech before it is treated as speech (milliseconds).
+    pub min_speech_ms: u64,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speech_threshold_db: -45.0,
+            silence_timeout_ms: 1500,
+            min_speech_ms: 250,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VadEvent {
+    SpeechStarted,
+    SpeechEnded,
+}
+
+/// Classifies 20ms frames of mono PCM16 audio as speech or silence and reports
+/// transitions once they have lasted long enough.
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    frame_len: usize,
+    frame_energy: f64,
+    frame_fill: usize,
+    speech_samples: u64,
+    silence_samples: u64,
+    min_speech_samples: u64,
+    silence_timeout_samples: u64,
+    in_speech: bool,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(config: VadConfig, sample_rate: u32) -> Self {
+        let samples_per_ms = sample_rate as u64 / 1000;
+        Self {
+            frame_len: (sample_rate as usize / 50).max(1),
+            frame_energy: 0.0,
+            frame_fill: 0,
+            speech_samples: 0,
+            silence_samples: 0,
+            min_speech_samples: config.min_speech_ms * samples_per_ms,
+            silence_timeout_samples: config.silence_timeout_ms * samples_per_ms,
+            in_speech: false,
+            config,
+        }
+    }
+
+    /// Forgets any speech in progress, for reuse across recordings.
+    pub fn reset(&mut self) {
+        self.frame_energy = 0.0;
+        self.frame_fill = 0;
+        self.speech_samples = 0;
+        self.silence_samples = 0;
+        self.in_speech = false;
+    }
+
+    /// Feeds mono samples and returns the last transition they caused, if any.
+    pub fn process(&mut self, samples: &[i16]) -> Option<VadEvent> {
+        let mut event = None;
+
+        for &sample in samples {
+            let normalized = sample as f64 / i16::MAX as f64;
+            self.frame_energy += normalized * normalized;
+            self.frame_fill += 1;
+
+            if self.frame_fill == self.frame_len {
+                let rms = (self.frame_energy / self.frame_len as f64).sqrt();
+                let level_db = 20.0 * rms.max(1e-9).log10();
+                self.frame_energy = 0.0;
+                self.frame_fill = 0;
+
+                if let Some(transition) = self.classify_frame(level_db as f32) {
+                    event = Some(transition);
+                }
+            }
+        }
+
+        event
+    }
+
+    fn classify_frame(&mut self, level_db: f32) -> Option<VadEvent> {
+        let frame = self.frame_len as u64;
+
+        if level_db >= self.config.speech_threshold_db {
+            self.speech_samples += frame;
+            self.silence_samples = 0;
+
+            if !self.in_speech && self.speech_samples >= self.min_speech_samples {
+                self.in_speech = true;
+                return Some(VadEvent::SpeechStarted);
+            }
+        } else {
+            self.silence_samples += frame;
+
+            if !self.in_speech {
+                // Short blips that never reached the minimum don't count
+                self.speech_samples = 0;
+            } else if self.silence_samples >= self.silence_timeout_samples {
+                self.in_speech = false;
+                self.speech_samples = 0;
+                return Some(VadEvent::SpeechEnded);
+            }
+        }
+
+        None
+    }
+}
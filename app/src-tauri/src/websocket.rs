@@ -1,15 +1,27 @@
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
-use tokio::sync::broadcast;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, oneshot, watch};
+use tokio_tungstenite::{
+    connect_async, tungstenite, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
 use uuid::Uuid;
 
+use crate::chunk_queue::{AudioChunkQueue, OutboundQueueConfig, QueueStatus};
+use crate::dictation_session::SessionTracker;
+use crate::error_handler::ErrorHandler;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketConfig {
     pub url: String,
     pub auto_reconnect: bool,
     pub reconnect_interval: u64, // seconds
+    /// Messages kept for the backend while the connection is down.
+    #[serde(default)]
+    pub queue: OutboundQueueConfig,
 }
 
 impl Default for WebSocketConfig {
@@ -18,377 +30,633 @@ impl Default for WebSocketConfig {
             url: "ws://localhost:8000/ws/transcribe".to_string(),
             auto_reconnect: true,
             reconnect_interval: 5,
+            queue: OutboundQueueConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TranscriptionMessage {
-    pub id: String,
-    pub audio_data: Vec<u8>,
-    pub timestamp: u64,
-}
-
+/// Payload of the `transcription_response` event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionResponse {
-    pub id: String,
     pub transcript: String,
     pub is_final: bool,
     pub enhanced_text: Option<String>,
 }
 
+/// Version of the protocol below, exchanged in `hello` when connecting.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Everything sent over the transcription socket, in both directions. Audio
+/// goes as binary frames; every other message is a JSON text frame tagged
+/// by `type`, as written by `backend/main.py`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProtocolMessage {
+    /// PCM16 audio, optionally behind a `ChunkHeader`.
+    #[serde(skip)]
+    Audio(Vec<u8>),
+    /// Control message sent by both sides when the connection opens.
+    Hello {
+        version: u32,
+    },
+    /// Raw transcript, interim until `is_final`.
+    Transcription {
+        text: String,
+        #[serde(default)]
+        is_final: bool,
+    },
+    /// Cleaned-up version of the last final transcript.
+    Enhanced {
+        text: String,
+        #[serde(default)]
+        is_final: bool,
+    },
+    Error {
+        #[serde(alias = "text")]
+        message: String,
+    },
+    /// Control message opening a dictation session. Its audio follows.
+    SessionStart {
+        session_id: String,
+        encoding: String,
+        sample_rate: u32,
+        channels: u16,
+        language: String,
+        mode: SessionMode,
+    },
+    /// Control message sent after the last audio of a session. The backend
+    /// flushes it and answers with `session_complete`, unless cancelled.
+    SessionEnd {
+        session_id: String,
+        #[serde(default)]
+        cancelled: bool,
+    },
+    SessionComplete(SessionComplete),
+    /// A message type from a newer backend.
+    #[serde(other)]
+    Unknown,
+}
+
+/// What a dictation session is for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionMode {
+    #[default]
+    Dictation,
+    Edit,
+}
+
+/// The backend's last word on a session, with all of its final text. Also
+/// the payload of the `session_complete` event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionComplete {
+    pub session_id: String,
+    pub text: String,
+    #[serde(default)]
+    pub enhanced_text: Option<String>,
+}
+
+impl ProtocolMessage {
+    pub fn into_frame(self) -> Result<Message, String> {
+        match self {
+            ProtocolMessage::Audio(data) => Ok(Message::Binary(data)),
+            message => serde_json::to_string(&message)
+                .map(Message::Text)
+                .map_err(|e| format!("Failed to encode message: {}", e)),
+        }
+    }
+
+    /// Decodes a data frame. Ping, pong and close frames give `None`.
+    pub fn from_frame(frame: Message) -> Result<Option<Self>, String> {
+        match frame {
+            Message::Binary(data) => Ok(Some(ProtocolMessage::Audio(data))),
+            Message::Text(text) => serde_json::from_str(&text)
+                .map(Some)
+                .map_err(|e| format!("Invalid message from backend: {}", e)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Acts on a message from the backend.
+fn handle_server_message(app: &AppHandle<tauri::Wry>, message: ProtocolMessage) {
+    match message {
+        ProtocolMessage::Hello { version } => {
+            if version != PROTOCOL_VERSION {
+                eprintln!(
+                    "Backend speaks protocol version {}, expected {}",
+                    version, PROTOCOL_VERSION
+                );
+            }
+        }
+        ProtocolMessage::Transcription { text, is_final } => {
+            let response = TranscriptionResponse {
+                transcript: text,
+                is_final,
+                enhanced_text: None,
+            };
+            let _ = app.emit("transcription_response", &response);
+        }
+        ProtocolMessage::Enhanced { text, .. } => {
+            let response = TranscriptionResponse {
+                transcript: text.clone(),
+                is_final: true,
+                enhanced_text: Some(text),
+            };
+            let _ = app.emit("transcription_response", &response);
+        }
+        ProtocolMessage::Error { message } => {
+            ErrorHandler::new(app.clone()).handle_websocket_error(
+                "The transcription service reported an error",
+                Some(&message),
+            );
+        }
+        ProtocolMessage::SessionComplete(result) => {
+            let _ = app.emit("session_complete", &result);
+        }
+        ProtocolMessage::Audio(_)
+        | ProtocolMessage::SessionStart { .. }
+        | ProtocolMessage::SessionEnd { .. }
+        | ProtocolMessage::Unknown => {}
+    }
+}
+
+/// Connection state, emitted on `websocket_status`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WebSocketStatus {
     Disconnected,
     Connecting,
     Connected,
     Error(String),
+    /// Waiting to retry after the connection failed or dropped.
+    Reconnecting {
+        /// Failed attempts in a row.
+        attempt: u32,
+        /// Unix time of the next attempt, in milliseconds.
+        next_retry_at: u64,
+        last_error: String,
+        /// Messages held until the connection is back.
+        queue: QueueStatus,
+    },
 }
 
-pub struct WebSocketClient {
-    app: AppHandle<tauri::Wry>,
+/// How long a connection attempt may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest wait between reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+/// How long a disconnect waits for the backend to answer the Close frame.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the queue in a `Reconnecting` status is brought up to date.
+const QUEUE_STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Receives what happens on the connection. The app passes it on to the UI;
+/// tests can watch it directly.
+pub trait ConnectionEvents: Send + Sync + 'static {
+    fn status_changed(&self, status: &WebSocketStatus);
+    fn message_received(&self, message: ProtocolMessage);
+    /// A queued message was written to the backend.
+    fn message_sent(&self, message: &ProtocolMessage);
+    /// Messages still queued were thrown away on disconnect.
+    fn queue_discarded(&self);
+}
+
+impl ConnectionEvents for AppHandle<tauri::Wry> {
+    fn status_changed(&self, status: &WebSocketStatus) {
+        let _ = self.emit("websocket_status", status);
+    }
+
+    fn message_received(&self, message: ProtocolMessage) {
+        handle_server_message(self, message);
+    }
+
+    fn message_sent(&self, message: &ProtocolMessage) {
+        // The backend's answer to a finished session is due from now on
+        if let ProtocolMessage::SessionEnd { session_id, .. } = message {
+            if let Some(sessions) = self.try_state::<SessionTracker>() {
+                sessions.end_sent(session_id);
+            }
+        }
+    }
+
+    fn queue_discarded(&self) {
+        if let Some(sessions) = self.try_state::<SessionTracker>() {
+            sessions.abandon();
+        }
+    }
+}
+
+/// State shared between the client and its connection supervisor.
+#[derive(Clone)]
+struct Connection {
     config: Arc<Mutex<WebSocketConfig>>,
     status: Arc<Mutex<WebSocketStatus>>,
     tx: Arc<Mutex<Option<broadcast::Sender<Message>>>>,
-    connection_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    audio_chunks: AudioChunkQueue,
+    events: Arc<dyn ConnectionEvents>,
 }
 
-impl WebSocketClient {
-    pub fn new(app: AppHandle<tauri::Wry>) -> Self {
-        Self {
-            app,
-            config: Arc::new(Mutex::new(WebSocketConfig::default())),
-            status: Arc::new(Mutex::new(WebSocketStatus::Disconnected)),
-            tx: Arc::new(Mutex::new(None)),
-            connection_handle: Arc::new(Mutex::new(None)),
+impl Connection {
+    fn set_status(&self, status: WebSocketStatus) {
+        *self.status.lock().unwrap() = status.clone();
+        self.events.status_changed(&status);
+    }
+}
+
+/// A running connection supervisor.
+struct Supervisor {
+    handle: tokio::task::JoinHandle<()>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Supervisor {
+    /// Asks the supervisor to close the socket and waits for it to stop,
+    /// aborting it if that takes longer than the close handshake may.
+    async fn stop(mut self) {
+        let _ = self.shutdown.send(true);
+        let grace = CLOSE_TIMEOUT + Duration::from_millis(500);
+        if tokio::time::timeout(grace, &mut self.handle).await.is_err() {
+            eprintln!("WebSocket supervisor did not stop in time, aborting it");
         }
+        self.handle.abort();
     }
+}
 
+#[derive(Clone)]
+pub struct WebSocketClient {
+    connection: Connection,
+    supervisor: Arc<Mutex<Option<Supervisor>>>,
+}
 
-    pub fn send_audio_data(&self, audio_data: Vec<u8>) -> Result<(), String> {
-        let message = TranscriptionMessage {
-            id: Uuid::new_v4().to_string(),
-            audio_data,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        };
+impl WebSocketClient {
+    /// Streamed audio arrives on `audio_chunks`, straight from the capture
+    /// thread, and is written as binary frames while connected. It is kept
+    /// there while the connection is down.
+    pub fn new(app: AppHandle<tauri::Wry>, audio_chunks: AudioChunkQueue) -> Self {
+        Self::with_events(Arc::new(app), audio_chunks)
+    }
 
-        let json = serde_json::to_string(&message)
-            .map_err(|e| format!("Failed to serialize message: {}", e))?;
+    pub fn with_events(events: Arc<dyn ConnectionEvents>, audio_chunks: AudioChunkQueue) -> Self {
+        Self {
+            connection: Connection {
+                config: Arc::new(Mutex::new(WebSocketConfig::default())),
+                status: Arc::new(Mutex::new(WebSocketStatus::Disconnected)),
+                tx: Arc::new(Mutex::new(None)),
+                audio_chunks,
+                events,
+            },
+            supervisor: Arc::new(Mutex::new(None)),
+        }
+    }
 
-        let tx_guard = self.tx.lock().unwrap();
-        if let Some(tx) = tx_guard.as_ref() {
-            tx.send(Message::Text(json))
-                .map_err(|e| format!("Failed to send message: {}", e))?;
-        } else {
+    /// Sends raw PCM16 audio as a single binary frame, after anything
+    /// already queued.
+    pub fn send_audio_data(&self, audio_data: Vec<u8>) -> Result<(), String> {
+        if self.supervisor.lock().unwrap().is_none() {
             return Err("WebSocket not connected".to_string());
         }
+        if !self
+            .connection
+            .audio_chunks
+            .push(ProtocolMessage::Audio(audio_data))
+        {
+            return Err("Outbound queue is full, audio dropped".to_string());
+        }
 
         Ok(())
     }
 
     pub fn get_status(&self) -> WebSocketStatus {
-        self.status.lock().unwrap().clone()
+        self.connection.status.lock().unwrap().clone()
     }
 
     pub fn update_config(&self, config: WebSocketConfig) {
-        let mut current_config = self.config.lock().unwrap();
+        self.connection.audio_chunks.configure(config.queue.clone());
+        let mut current_config = self.connection.config.lock().unwrap();
         *current_config = config;
     }
 
     pub fn get_config(&self) -> WebSocketConfig {
-        self.config.lock().unwrap().clone()
+        self.connection.config.lock().unwrap().clone()
     }
 
+    /// Starts the connection supervisor, stopping one that is running, and
+    /// waits for the outcome of its first attempt. The supervisor keeps
+    /// reconnecting after that if enabled.
+    pub async fn connect(&self) -> Result<(), String> {
+        let previous = self.supervisor.lock().unwrap().take();
+        if let Some(previous) = previous {
+            previous.stop().await;
+        }
+
+        self.connection.audio_chunks.set_enabled(true);
+        let (first_attempt, result) = oneshot::channel();
+        let (shutdown, shutdown_requested) = watch::channel(false);
+        {
+            // Held until the supervisor is in place, in case it gives up at once
+            let mut slot = self.supervisor.lock().unwrap();
+            let handle = tokio::spawn(supervise(
+                self.connection.clone(),
+                self.supervisor.clone(),
+                first_attempt,
+                shutdown_requested,
+            ));
+            *slot = Some(Supervisor { handle, shutdown });
+        }
+
+        result
+            .await
+            .unwrap_or_else(|_| Err("Connection supervisor stopped".to_string()))
+    }
+
+    /// Closes the socket gracefully and stops reconnecting until `connect` is
+    /// called again.
+    pub async fn disconnect(&self) {
+        let supervisor = self.supervisor.lock().unwrap().take();
+        if let Some(supervisor) = supervisor {
+            supervisor.stop().await;
+        }
+
+        // The supervisor may have been aborted before cleaning up
+        *self.connection.tx.lock().unwrap() = None;
+        self.connection.audio_chunks.set_enabled(false);
+        self.connection.events.queue_discarded();
+        self.connection.set_status(WebSocketStatus::Disconnected);
+    }
 }
 
 // Global WebSocket client state
 pub type WebSocketClientState = Arc<Mutex<WebSocketClient>>;
 
-// Tauri commands
-#[tauri::command]
-pub async fn connect_websocket(
-    state: tauri::State<'_, WebSocketClientState>,
-) -> Result<(), String> {
-    // Get the config and app handle without holding the lock
-    let (config, app) = {
-        let client = state.lock().unwrap();
-        (client.get_config(), client.app.clone())
-    };
-    
-    // Update status to connecting
-    {
-        let client = state.lock().unwrap();
-        let mut status = client.status.lock().unwrap();
-        *status = WebSocketStatus::Connecting;
-        let _ = app.emit("websocket_status", &WebSocketStatus::Connecting);
-    }
-    
-    // Try to connect without holding the main lock with timeout
-    let connect_future = connect_async(&config.url);
-    let timeout_duration = tokio::time::Duration::from_secs(10); // 10 second timeout
-    
-    match tokio::time::timeout(timeout_duration, connect_future).await {
-        Ok(Ok((ws_stream, _))) => {
-            // Connection successful
-            {
-                let client = state.lock().unwrap();
-                let mut status = client.status.lock().unwrap();
-                *status = WebSocketStatus::Connected;
-                let _ = app.emit("websocket_status", &WebSocketStatus::Connected);
-            }
-            
-            // Keep connection open and start message handling
-            use futures_util::{SinkExt, StreamExt};
-            use tokio::sync::broadcast;
-            
-            let (mut write, mut read) = ws_stream.split();
-            let (tx, mut rx) = broadcast::channel::<Message>(32);
-            
-            // Store the sender for sending messages
-            {
-                let client = state.lock().unwrap();
-                let mut sender = client.tx.lock().unwrap();
-                *sender = Some(tx);
-            }
-            
-            let app_clone = app.clone();
-            let state_clone = state.inner().clone();
-            
-            // Start the main connection handler task
-            let handle = tokio::spawn(async move {
-                // Handle incoming messages
-                let app_for_read = app_clone.clone();
-                let state_for_read = state_clone.clone();
-                let read_task = tokio::spawn(async move {
-                    while let Some(msg) = read.next().await {
-                        match msg {
-                            Ok(Message::Text(text)) => {
-                                if let Ok(response) = serde_json::from_str::<TranscriptionResponse>(&text) {
-                                    let _ = app_for_read.emit("transcription_response", &response);
-                                }
-                            }
-                            Ok(Message::Close(_)) => {
-                                {
-                                    let client = state_for_read.lock().unwrap();
-                                    let mut status = client.status.lock().unwrap();
-                                    *status = WebSocketStatus::Disconnected;
-                                }
-                                let _ = app_for_read.emit("websocket_status", &WebSocketStatus::Disconnected);
-                                break;
-                            }
-                            Err(e) => {
-                                let error_msg = format!("WebSocket error: {}", e);
-                                {
-                                    let client = state_for_read.lock().unwrap();
-                                    let mut status = client.status.lock().unwrap();
-                                    *status = WebSocketStatus::Error(error_msg.clone());
-                                }
-                                let _ = app_for_read.emit("websocket_status", &WebSocketStatus::Error(error_msg));
-                                break;
-                            }
-                            _ => {}
-                        }
-                    }
-                    // Clean up sender on disconnect
-                    {
-                        let client = state_for_read.lock().unwrap();
-                        let mut sender = client.tx.lock().unwrap();
-                        *sender = None;
-                    }
-                });
-
-                // Handle outgoing messages
-                let write_task = tokio::spawn(async move {
-                    while let Ok(msg) = rx.recv().await {
-                        if let Err(e) = write.send(msg).await {
-                            eprintln!("Failed to send WebSocket message: {}", e);
-                            break;
-                        }
-                    }
-                });
-
-                // Wait for either task to complete
-                tokio::select! {
-                    _ = read_task => {
-                        println!("WebSocket read task completed");
-                    },
-                    _ = write_task => {
-                        println!("WebSocket write task completed");
-                    },
+/// Owns the socket: connects, runs the connection until it ends, then
+/// reconnects with backoff for as long as `auto_reconnect` is on. Stops,
+/// closing the socket, once `shutdown` is set. When it gives up it empties
+/// `supervisor`, so the client reports that it is not connected.
+async fn supervise(
+    connection: Connection,
+    supervisor: Arc<Mutex<Option<Supervisor>>>,
+    first_attempt: oneshot::Sender<Result<(), String>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut first_attempt = Some(first_attempt);
+    let mut attempt = 0;
+
+    loop {
+        let url = connection.config.lock().unwrap().url.clone();
+        connection.set_status(WebSocketStatus::Connecting);
+
+        let connected = tokio::select! {
+            connected = tokio::time::timeout(CONNECT_TIMEOUT, connect_async(&url)) => connected,
+            _ = shutdown.changed() => break,
+        };
+        let result = match connected {
+            Ok(Ok((stream, _))) => {
+                println!("WebSocket connected successfully to: {}", url);
+                attempt = 0;
+                connection.set_status(WebSocketStatus::Connected);
+                if let Some(reply) = first_attempt.take() {
+                    let _ = reply.send(Ok(()));
                 }
-            });
-            
-            // Store the connection handle
-            {
-                let client = state.lock().unwrap();
-                let mut connection_handle = client.connection_handle.lock().unwrap();
-                *connection_handle = Some(handle);
+                run_connection(&connection, stream, &mut shutdown).await
             }
-            
-            println!("WebSocket connected successfully to: {}", config.url);
-            Ok(())
+            Ok(Err(e)) => Err(format!("Failed to connect to WebSocket: {}", e)),
+            Err(_) => Err(format!(
+                "Connection timeout after {} seconds to: {}",
+                CONNECT_TIMEOUT.as_secs(),
+                url
+            )),
+        };
+        if *shutdown.borrow() {
+            break;
         }
-        Ok(Err(e)) => {
-            // Connection failed
-            let error_msg = format!("Failed to connect to WebSocket: {}", e);
-            println!("Connection error: {}", error_msg);
-            
-            // Update status to error
+
+        let closed = result.is_ok();
+        let error = match result {
+            Ok(()) => "Connection closed by the backend".to_string(),
+            Err(e) => e,
+        };
+        println!("WebSocket connection ended: {}", error);
+        if let Some(reply) = first_attempt.take() {
+            let _ = reply.send(Err(error.clone()));
+        }
+
+        // Settings may have changed while connected
+        let config = connection.config.lock().unwrap().clone();
+        if !config.auto_reconnect {
+            // Nothing will send what is still queued
+            connection.audio_chunks.set_enabled(false);
+            connection.events.queue_discarded();
             {
-                let client = state.lock().unwrap();
-                let mut status = client.status.lock().unwrap();
-                *status = WebSocketStatus::Error(error_msg.clone());
-                let _ = app.emit("websocket_status", &WebSocketStatus::Error(error_msg.clone()));
+                let mut slot = supervisor.lock().unwrap();
+                if slot
+                    .as_ref()
+                    .is_some_and(|running| running.handle.id() == tokio::task::id())
+                {
+                    *slot = None;
+                }
             }
-            
-                         // Check if auto-reconnect is enabled
-             if config.auto_reconnect {
-                 println!("Auto-reconnect is enabled, scheduling reconnection in {} seconds", config.reconnect_interval);
-                 
-                 // Schedule reconnection by emitting a delayed event
-                 let app_clone = app.clone();
-                 let interval = config.reconnect_interval;
-                 
-                 tokio::spawn(async move {
-                     tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
-                     println!("Attempting to reconnect...");
-                     
-                     // Emit reconnection event
-                     let _ = app_clone.emit("websocket_reconnect", ());
-                 });
-             }
-            
-            Err(error_msg)
-        }
-        Err(_) => {
-            // Timeout occurred
-            let error_msg = format!("Connection timeout after 10 seconds to: {}", config.url);
-            println!("Connection timeout: {}", error_msg);
-            
-            // Update status to error
-            {
-                let client = state.lock().unwrap();
-                let mut status = client.status.lock().unwrap();
-                *status = WebSocketStatus::Error(error_msg.clone());
-                let _ = app.emit("websocket_status", &WebSocketStatus::Error(error_msg.clone()));
+            connection.set_status(if closed {
+                WebSocketStatus::Disconnected
+            } else {
+                WebSocketStatus::Error(error)
+            });
+            break;
+        }
+
+        attempt += 1;
+        let delay = backoff_delay(Duration::from_secs(config.reconnect_interval), attempt);
+        println!(
+            "Reconnecting in {:.1} seconds (attempt {})",
+            delay.as_secs_f32(),
+            attempt
+        );
+        let next_retry_at = unix_millis() + delay.as_millis() as u64;
+        let retry = tokio::time::sleep(delay);
+        tokio::pin!(retry);
+
+        // Report the wait, then how the queue fills up during it
+        let reconnecting = |queue| WebSocketStatus::Reconnecting {
+            attempt,
+            next_retry_at,
+            last_error: error.clone(),
+            queue,
+        };
+        let mut queue = connection.audio_chunks.status();
+        connection.set_status(reconnecting(queue.clone()));
+        let mut refresh = tokio::time::interval_at(
+            tokio::time::Instant::now() + QUEUE_STATUS_INTERVAL,
+            QUEUE_STATUS_INTERVAL,
+        );
+        loop {
+            tokio::select! {
+                _ = &mut retry => break,
+                _ = refresh.tick() => {
+                    let current = connection.audio_chunks.status();
+                    if current != queue {
+                        queue = current;
+                        connection.set_status(reconnecting(queue.clone()));
+                    }
+                }
+                _ = shutdown.changed() => return,
             }
-            
-                         // Check if auto-reconnect is enabled
-             if config.auto_reconnect {
-                 println!("Auto-reconnect is enabled, scheduling reconnection in {} seconds", config.reconnect_interval);
-                 
-                 // Schedule reconnection by emitting a delayed event
-                 let app_clone = app.clone();
-                 let interval = config.reconnect_interval;
-                 
-                 tokio::spawn(async move {
-                     tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
-                     println!("Attempting to reconnect...");
-                     
-                     // Emit reconnection event
-                     let _ = app_clone.emit("websocket_reconnect", ());
-                 });
-             }
-            
-            Err(error_msg)
         }
     }
 }
 
-#[tauri::command]
-pub async fn disconnect_websocket(
-    state: tauri::State<'_, WebSocketClientState>,
+/// Passes messages both ways until the connection ends or `shutdown` changes.
+/// Returns an error if it failed rather than being closed.
+async fn run_connection(
+    connection: &Connection,
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    shutdown: &mut watch::Receiver<bool>,
 ) -> Result<(), String> {
-    // Get the app handle without holding the lock
-    let app = {
-        let client = state.lock().unwrap();
-        client.app.clone()
+    let (mut write, mut read) = stream.split();
+    let (tx, mut rx) = broadcast::channel::<Message>(32);
+
+    // Announce the protocol version before anything else is written
+    let hello = ProtocolMessage::Hello {
+        version: PROTOCOL_VERSION,
     };
-    
-    // Update status to disconnected
-    {
-        let client = state.lock().unwrap();
-        let mut status = client.status.lock().unwrap();
-        *status = WebSocketStatus::Disconnected;
-        let _ = app.emit("websocket_status", &WebSocketStatus::Disconnected);
-    }
-    
-    Ok(())
-}
+    let _ = tx.send(hello.into_frame()?);
+    *connection.tx.lock().unwrap() = Some(tx);
+    connection.audio_chunks.resume();
 
-// Manual reconnection function for event-based reconnection
-pub async fn reconnect_websocket(ws_client: &WebSocketClientState) -> Result<(), String> {
-    // Get the config and app handle without holding the lock
-    let (config, app) = {
-        let client = ws_client.lock().unwrap();
-        (client.get_config(), client.app.clone())
+    let reading = async {
+        while let Some(frame) = read.next().await {
+            match frame {
+                Ok(Message::Close(_)) => return Ok(()),
+                Ok(frame) => match ProtocolMessage::from_frame(frame) {
+                    Ok(Some(message)) => connection.events.message_received(message),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("{}", e),
+                },
+                Err(e) => return Err(format!("WebSocket error: {}", e)),
+            }
+        }
+        Ok(())
     };
-    
-    // Update status to connecting
-    {
-        let client = ws_client.lock().unwrap();
-        let mut status = client.status.lock().unwrap();
-        *status = WebSocketStatus::Connecting;
-        let _ = app.emit("websocket_status", &WebSocketStatus::Connecting);
-    }
-    
-    // Try to connect without holding the main lock with timeout
-    let connect_future = connect_async(&config.url);
-    let timeout_duration = tokio::time::Duration::from_secs(10); // 10 second timeout
-    
-    match tokio::time::timeout(timeout_duration, connect_future).await {
-        Ok(Ok((ws_stream, _))) => {
-            // Connection successful
-            {
-                let client = ws_client.lock().unwrap();
-                let mut status = client.status.lock().unwrap();
-                *status = WebSocketStatus::Connected;
-                let _ = app.emit("websocket_status", &WebSocketStatus::Connected);
+
+    // Outgoing messages first, then queued audio and session control. A
+    // queued message stays in `in_flight` until it is written, so it
+    // survives the write being cut off.
+    let mut in_flight = None;
+    let writing = async {
+        loop {
+            let frame = tokio::select! {
+                biased;
+                frame = rx.recv() => match frame {
+                    Ok(frame) => frame,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                message = connection.audio_chunks.pop() => match message.clone().into_frame() {
+                    Ok(frame) => {
+                        in_flight = Some(message);
+                        frame
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+            };
+            write
+                .send(frame)
+                .await
+                .map_err(|e| format!("Failed to send WebSocket message: {}", e))?;
+            if let Some(message) = in_flight.take() {
+                connection.events.message_sent(&message);
             }
-            
-            // For now, just close the connection immediately
-            // In a real implementation, we'd keep it open and handle messages
-            drop(ws_stream);
-            
-            println!("WebSocket reconnected successfully to: {}", config.url);
-            Ok(())
         }
-        Ok(Err(e)) => {
-            // Connection failed
-            let error_msg = format!("Failed to reconnect to WebSocket: {}", e);
-            println!("Reconnection error: {}", error_msg);
-            
-            // Update status to error
-            {
-                let client = ws_client.lock().unwrap();
-                let mut status = client.status.lock().unwrap();
-                *status = WebSocketStatus::Error(error_msg.clone());
-                let _ = app.emit("websocket_status", &WebSocketStatus::Error(error_msg.clone()));
+    };
+
+    let ended = tokio::select! {
+        result = reading => Some(result),
+        result = writing => Some(result),
+        _ = shutdown.changed() => None,
+    };
+
+    // Keep an unwritten message for the next connection
+    if let Some(message) = in_flight.take() {
+        connection.audio_chunks.requeue(message);
+    }
+    let result = match ended {
+        Some(result) => result,
+        None => {
+            if let Ok(stream) = write.reunite(read) {
+                close_gracefully(stream).await;
             }
-            
-            Err(error_msg)
-        }
-        Err(_) => {
-            // Timeout occurred
-            let error_msg = format!("Reconnection timeout after 10 seconds to: {}", config.url);
-            println!("Reconnection timeout: {}", error_msg);
-            
-            // Update status to error
-            {
-                let client = ws_client.lock().unwrap();
-                let mut status = client.status.lock().unwrap();
-                *status = WebSocketStatus::Error(error_msg.clone());
-                let _ = app.emit("websocket_status", &WebSocketStatus::Error(error_msg.clone()));
+            Ok(())
+        }
+    };
+
+    // Clean up sender on disconnect; queued messages wait for the next one
+    *connection.tx.lock().unwrap() = None;
+    result
+}
+
+/// Sends a Close frame and waits for the backend to answer it.
+async fn close_gracefully(mut stream: WebSocketStream<MaybeTlsStream<TcpStream>>) {
+    let handshake = async {
+        stream.close(None).await?;
+        // Skip whatever is still in flight up to the backend's Close frame
+        while let Some(frame) = stream.next().await {
+            if let Message::Close(_) = frame? {
+                break;
             }
-            
-            Err(error_msg)
         }
+        Ok::<_, tungstenite::Error>(())
+    };
+
+    match tokio::time::timeout(CLOSE_TIMEOUT, handshake).await {
+        Ok(Ok(())) => println!("WebSocket closed"),
+        Ok(Err(e)) => eprintln!("Failed to close WebSocket cleanly: {}", e),
+        Err(_) => eprintln!(
+            "Backend did not answer the close within {} seconds",
+            CLOSE_TIMEOUT.as_secs()
+        ),
     }
 }
 
+/// Delay before reconnection attempt `attempt`, counting from 1: the
+/// configured interval, doubled after every failure up to a cap, give or
+/// take 20% so that clients do not retry in lockstep.
+fn backoff_delay(interval: Duration, attempt: u32) -> Duration {
+    let interval = interval.max(Duration::from_secs(1));
+    let delay = interval
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RECONNECT_DELAY.max(interval));
+
+    // The v4 UUID generator doubles as a source of randomness
+    let random = (Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+    delay.mul_f64(0.8 + 0.4 * random)
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+// Tauri commands
+#[tauri::command]
+pub async fn connect_websocket(
+    state: tauri::State<'_, WebSocketClientState>,
+) -> Result<(), String> {
+    let client = state.lock().unwrap().clone();
+    client.connect().await
+}
+
+#[tauri::command]
+pub async fn disconnect_websocket(
+    state: tauri::State<'_, WebSocketClientState>,
+) -> Result<(), String> {
+    let client = state.lock().unwrap().clone();
+    client.disconnect().await;
+
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_websocket_status(
     state: tauri::State<'_, WebSocketClientState>,
@@ -422,4 +690,531 @@ pub fn send_audio_data(
 ) -> Result<(), String> {
     let client = state.lock().unwrap();
     client.send_audio_data(audio_data)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio::net::TcpListener;
+
+    // Frames as sent by backend/main.py (Starlette's send_json uses json.dumps)
+    const BACKEND_HELLO: &str = r#"{"type": "hello", "version": 1}"#;
+    const BACKEND_INTERIM: &str =
+        r#"{"type": "transcription", "text": "send the report", "is_final": false}"#;
+    const BACKEND_FINAL: &str =
+        r#"{"type": "transcription", "text": "send the report to anna", "is_final": true}"#;
+    const BACKEND_ENHANCED: &str =
+        r#"{"type": "enhanced", "text": "Send the report to Anna.", "is_final": true}"#;
+    const BACKEND_ERROR: &str = r#"{"type": "error", "message": "Transcription error occurred"}"#;
+    const BACKEND_SESSION_COMPLETE: &str = concat!(
+        r#"{"type": "session_complete", "session_id": "6f1c2a4e-8d1b-4c3a-9f55-2b7e0d9a1c33", "#,
+        r#""text": "send the report to anna", "enhanced_text": "Send the report to Anna."}"#
+    );
+
+    fn decode(text: &str) -> ProtocolMessage {
+        ProtocolMessage::from_frame(Message::Text(text.to_string()))
+            .unwrap()
+            .unwrap()
+    }
+
+    fn round_trip(message: ProtocolMessage) -> ProtocolMessage {
+        let frame = message.into_frame().unwrap();
+        ProtocolMessage::from_frame(frame).unwrap().unwrap()
+    }
+
+    #[test]
+    fn decodes_backend_payloads() {
+        assert_eq!(decode(BACKEND_HELLO), ProtocolMessage::Hello { version: 1 });
+        assert_eq!(
+            decode(BACKEND_INTERIM),
+            ProtocolMessage::Transcription {
+                text: "send the report".to_string(),
+                is_final: false,
+            }
+        );
+        assert_eq!(
+            decode(BACKEND_FINAL),
+            ProtocolMessage::Transcription {
+                text: "send the report to anna".to_string(),
+                is_final: true,
+            }
+        );
+        assert_eq!(
+            decode(BACKEND_ENHANCED),
+            ProtocolMessage::Enhanced {
+                text: "Send the report to Anna.".to_string(),
+                is_final: true,
+            }
+        );
+        assert_eq!(
+            decode(BACKEND_ERROR),
+            ProtocolMessage::Error {
+                message: "Transcription error occurred".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn backend_payloads_round_trip() {
+        for payload in [
+            BACKEND_HELLO,
+            BACKEND_INTERIM,
+            BACKEND_FINAL,
+            BACKEND_ENHANCED,
+            BACKEND_ERROR,
+            BACKEND_SESSION_COMPLETE,
+        ] {
+            let message = decode(payload);
+            assert_eq!(round_trip(message.clone()), message, "{}", payload);
+
+            // Same fields on the wire as the backend wrote
+            let Message::Text(text) = message.into_frame().unwrap() else {
+                panic!("expected a text frame for {}", payload);
+            };
+            let ours: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let theirs: serde_json::Value = serde_json::from_str(payload).unwrap();
+            assert_eq!(ours, theirs);
+        }
+    }
+
+    #[test]
+    fn decodes_session_complete() {
+        assert_eq!(
+            decode(BACKEND_SESSION_COMPLETE),
+            ProtocolMessage::SessionComplete(SessionComplete {
+                session_id: "6f1c2a4e-8d1b-4c3a-9f55-2b7e0d9a1c33".to_string(),
+                text: "send the report to anna".to_string(),
+                enhanced_text: Some("Send the report to Anna.".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn session_control_round_trips() {
+        let start = ProtocolMessage::SessionStart {
+            session_id: "6f1c2a4e-8d1b-4c3a-9f55-2b7e0d9a1c33".to_string(),
+            encoding: "linear16".to_string(),
+            sample_rate: 16000,
+            channels: 1,
+            language: "en-US".to_string(),
+            mode: SessionMode::Dictation,
+        };
+        let end = ProtocolMessage::SessionEnd {
+            session_id: "6f1c2a4e-8d1b-4c3a-9f55-2b7e0d9a1c33".to_string(),
+            cancelled: false,
+        };
+        assert_eq!(round_trip(start.clone()), start);
+        assert_eq!(round_trip(end.clone()), end);
+
+        let Message::Text(text) = start.into_frame().unwrap() else {
+            panic!("expected a text frame");
+        };
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["type"], "session_start");
+        assert_eq!(json["mode"], "dictation");
+    }
+
+    #[test]
+    fn audio_travels_as_binary() {
+        let pcm = vec![0x01, 0x00, 0xff, 0x7f];
+        let frame = ProtocolMessage::Audio(pcm.clone()).into_frame().unwrap();
+        assert_eq!(frame, Message::Binary(pcm.clone()));
+        assert_eq!(
+            ProtocolMessage::from_frame(frame).unwrap(),
+            Some(ProtocolMessage::Audio(pcm))
+        );
+    }
+
+    #[test]
+    fn fills_in_optional_fields() {
+        assert_eq!(
+            decode(r#"{"type": "transcription", "text": "hi"}"#),
+            ProtocolMessage::Transcription {
+                text: "hi".to_string(),
+                is_final: false,
+            }
+        );
+        assert_eq!(
+            decode(r#"{"type": "error", "text": "quota exceeded"}"#),
+            ProtocolMessage::Error {
+                message: "quota exceeded".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn tolerates_newer_message_types() {
+        assert_eq!(
+            decode(r#"{"type": "speaker_change", "speaker": 2}"#),
+            ProtocolMessage::Unknown
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        assert!(ProtocolMessage::from_frame(Message::Text("not json".to_string())).is_err());
+        let untagged = Message::Text(r#"{"text": "no type"}"#.to_string());
+        assert!(ProtocolMessage::from_frame(untagged).is_err());
+        assert_eq!(
+            ProtocolMessage::from_frame(Message::Ping(Vec::new())).unwrap(),
+            None
+        );
+    }
+
+    /// Keeps the control messages written to the backend.
+    #[derive(Default)]
+    struct SentControl(Mutex<Vec<ProtocolMessage>>);
+
+    impl SentControl {
+        fn messages(&self) -> Vec<ProtocolMessage> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl ConnectionEvents for SentControl {
+        fn status_changed(&self, _status: &WebSocketStatus) {}
+        fn message_received(&self, _message: ProtocolMessage) {}
+        fn message_sent(&self, message: &ProtocolMessage) {
+            if !matches!(message, ProtocolMessage::Audio(_)) {
+                self.0.lock().unwrap().push(message.clone());
+            }
+        }
+        fn queue_discarded(&self) {}
+    }
+
+    /// A client for a backend on `url` that retries after about a second.
+    fn test_client(url: &str) -> WebSocketClient {
+        test_client_with_events(url, Arc::new(SentControl::default()))
+    }
+
+    fn test_client_with_events(url: &str, events: Arc<dyn ConnectionEvents>) -> WebSocketClient {
+        let client = WebSocketClient::with_events(
+            events,
+            AudioChunkQueue::new(OutboundQueueConfig::default(), None),
+        );
+        client.update_config(WebSocketConfig {
+            url: url.to_string(),
+            auto_reconnect: true,
+            reconnect_interval: 1,
+            queue: OutboundQueueConfig::default(),
+        });
+        client
+    }
+
+    async fn local_backend() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        (listener, url)
+    }
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio_tungstenite::accept_async(stream).await.unwrap()
+    }
+
+    /// Waits for the client to announce itself.
+    async fn expect_hello(backend: &mut WebSocketStream<TcpStream>) {
+        let frame = backend.next().await.unwrap().unwrap();
+        assert_eq!(
+            ProtocolMessage::from_frame(frame).unwrap(),
+            Some(ProtocolMessage::Hello {
+                version: PROTOCOL_VERSION
+            })
+        );
+    }
+
+    fn is_connected(client: &WebSocketClient) -> bool {
+        matches!(client.get_status(), WebSocketStatus::Connected)
+    }
+
+    #[tokio::test]
+    async fn disconnect_closes_the_socket() {
+        let (listener, url) = local_backend().await;
+        let client = test_client(&url);
+        let (connected, backend) = tokio::join!(client.connect(), accept(&listener));
+        connected.unwrap();
+        let mut backend = backend;
+        expect_hello(&mut backend).await;
+
+        let started = Instant::now();
+        let (_, close) = tokio::join!(client.disconnect(), async {
+            let close = backend.next().await;
+            // Reading on sends the close reply and sees the socket end
+            while backend.next().await.is_some() {}
+            close
+        });
+
+        assert!(matches!(close, Some(Ok(Message::Close(_)))));
+        assert!(started.elapsed() < CLOSE_TIMEOUT);
+        assert!(matches!(client.get_status(), WebSocketStatus::Disconnected));
+        assert!(client.connection.tx.lock().unwrap().is_none());
+        assert!(client.send_audio_data(vec![0; 4]).is_err());
+
+        // A user-initiated disconnect is not retried
+        let retry = tokio::time::timeout(Duration::from_secs(2), listener.accept()).await;
+        assert!(retry.is_err());
+    }
+
+    #[tokio::test]
+    async fn disconnect_gives_up_on_a_silent_backend() {
+        let (listener, url) = local_backend().await;
+        let client = test_client(&url);
+        // Accept but never read, so the Close frame is not answered
+        let (connected, _backend) = tokio::join!(client.connect(), accept(&listener));
+        connected.unwrap();
+
+        let started = Instant::now();
+        client.disconnect().await;
+        let elapsed = started.elapsed();
+
+        assert!(elapsed >= CLOSE_TIMEOUT);
+        assert!(elapsed < CLOSE_TIMEOUT + Duration::from_secs(1));
+        assert!(matches!(client.get_status(), WebSocketStatus::Disconnected));
+        assert!(client.supervisor.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn disconnect_cancels_a_pending_retry() {
+        let (listener, url) = local_backend().await;
+        drop(listener);
+        let client = test_client(&url);
+
+        assert!(client.connect().await.is_err());
+        assert!(matches!(
+            client.get_status(),
+            WebSocketStatus::Reconnecting { attempt: 1, .. }
+        ));
+
+        let started = Instant::now();
+        client.disconnect().await;
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(matches!(client.get_status(), WebSocketStatus::Disconnected));
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_backend_drops() {
+        let (listener, url) = local_backend().await;
+        let client = test_client(&url);
+        let (connected, backend) = tokio::join!(client.connect(), accept(&listener));
+        connected.unwrap();
+        drop(backend);
+
+        let mut backend = tokio::time::timeout(Duration::from_secs(5), accept(&listener))
+            .await
+            .expect("client did not reconnect");
+        expect_hello(&mut backend).await;
+        while !is_connected(&client) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        tokio::join!(client.disconnect(), async {
+            while backend.next().await.is_some() {}
+        });
+        assert!(matches!(client.get_status(), WebSocketStatus::Disconnected));
+    }
+
+    async fn next_message(backend: &mut WebSocketStream<TcpStream>) -> ProtocolMessage {
+        let frame = backend.next().await.unwrap().unwrap();
+        ProtocolMessage::from_frame(frame).unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_queued_messages_after_reconnecting() {
+        let (listener, url) = local_backend().await;
+        let events = Arc::new(SentControl::default());
+        let client = test_client_with_events(&url, events.clone());
+        let (connected, backend) = tokio::join!(client.connect(), accept(&listener));
+        connected.unwrap();
+        let mut backend = backend;
+        expect_hello(&mut backend).await;
+
+        let queue = &client.connection.audio_chunks;
+        let start = ProtocolMessage::SessionStart {
+            session_id: "5f0c1f5e-3a57-4b6e-9d43-1c2b3a4d5e6f".to_string(),
+            encoding: "linear16".to_string(),
+            sample_rate: 16000,
+            channels: 1,
+            language: "en-US".to_string(),
+            mode: SessionMode::Dictation,
+        };
+        let end = ProtocolMessage::SessionEnd {
+            session_id: "5f0c1f5e-3a57-4b6e-9d43-1c2b3a4d5e6f".to_string(),
+            cancelled: false,
+        };
+        assert!(queue.push(start.clone()));
+        assert!(queue.push(ProtocolMessage::Audio(vec![1; 8])));
+        assert_eq!(next_message(&mut backend).await, start);
+        assert_eq!(
+            next_message(&mut backend).await,
+            ProtocolMessage::Audio(vec![1; 8])
+        );
+
+        // The rest of the session is queued while the backend is away
+        drop(backend);
+        while !matches!(client.get_status(), WebSocketStatus::Reconnecting { .. }) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(queue.push(ProtocolMessage::Audio(vec![2; 8])));
+        assert!(client.send_audio_data(vec![3; 8]).is_ok());
+        assert!(queue.push(end.clone()));
+        assert_eq!(queue.depth(), 3);
+        // Queued is not sent; the session's timeout has not started
+        assert_eq!(events.messages(), vec![start.clone()]);
+
+        // and replayed in order after the session is started again
+        let mut backend = tokio::time::timeout(Duration::from_secs(5), accept(&listener))
+            .await
+            .expect("client did not reconnect");
+        expect_hello(&mut backend).await;
+        assert_eq!(next_message(&mut backend).await, start);
+        assert_eq!(
+            next_message(&mut backend).await,
+            ProtocolMessage::Audio(vec![2; 8])
+        );
+        assert_eq!(
+            next_message(&mut backend).await,
+            ProtocolMessage::Audio(vec![3; 8])
+        );
+        assert_eq!(next_message(&mut backend).await, end);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while events.messages().len() < 3 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(events.messages(), vec![start.clone(), start, end]);
+
+        tokio::join!(client.disconnect(), async {
+            while backend.next().await.is_some() {}
+        });
+        assert_eq!(queue.depth(), 0);
+    }
+
+    /// Keeps the tag, the first byte, of every audio chunk written.
+    #[derive(Default)]
+    struct SentAudio(Mutex<Vec<u8>>);
+
+    impl ConnectionEvents for SentAudio {
+        fn status_changed(&self, _status: &WebSocketStatus) {}
+        fn message_received(&self, _message: ProtocolMessage) {}
+        fn message_sent(&self, message: &ProtocolMessage) {
+            if let ProtocolMessage::Audio(pcm) = message {
+                self.0.lock().unwrap().push(pcm[0]);
+            }
+        }
+        fn queue_discarded(&self) {}
+    }
+
+    #[tokio::test]
+    async fn replays_a_message_cut_off_mid_write() {
+        let (listener, url) = local_backend().await;
+        let events = Arc::new(SentAudio::default());
+        let client = test_client_with_events(&url, events.clone());
+        let (connected, backend) = tokio::join!(client.connect(), accept(&listener));
+        connected.unwrap();
+        let mut backend = backend;
+        expect_hello(&mut backend).await;
+
+        // More than the socket buffers hold while the backend is not reading,
+        // so writing stalls partway through a chunk
+        let queue = &client.connection.audio_chunks;
+        for tag in 1..=8 {
+            assert!(queue.push(ProtocolMessage::Audio(vec![tag; 4 << 20])));
+        }
+        let mut depth = queue.depth();
+        loop {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            if queue.depth() == depth {
+                break;
+            }
+            depth = queue.depth();
+        }
+
+        // The backend hangs up in the middle of it
+        backend.send(Message::Close(None)).await.unwrap();
+        let mut backend = tokio::time::timeout(Duration::from_secs(5), accept(&listener))
+            .await
+            .expect("client did not reconnect");
+        expect_hello(&mut backend).await;
+
+        let sent = events.0.lock().unwrap().clone();
+        let cut_off = sent.last().map_or(1, |tag| tag + 1);
+        assert!(cut_off <= 8, "no write was cut off");
+        for tag in cut_off..=8 {
+            let ProtocolMessage::Audio(pcm) = next_message(&mut backend).await else {
+                panic!("expected audio");
+            };
+            assert_eq!(pcm[0], tag);
+        }
+
+        tokio::join!(client.disconnect(), async {
+            while backend.next().await.is_some() {}
+        });
+    }
+
+    /// Reports to a session tracker like the app does.
+    struct TrackedSessions(SessionTracker);
+
+    impl ConnectionEvents for TrackedSessions {
+        fn status_changed(&self, _status: &WebSocketStatus) {}
+        fn message_received(&self, _message: ProtocolMessage) {}
+        fn message_sent(&self, message: &ProtocolMessage) {
+            if let ProtocolMessage::SessionEnd { session_id, .. } = message {
+                self.0.end_sent(session_id);
+            }
+        }
+        fn queue_discarded(&self) {
+            self.0.abandon();
+        }
+    }
+
+    #[tokio::test]
+    async fn abandons_the_session_when_not_reconnecting() {
+        let (listener, url) = local_backend().await;
+        let sessions = SessionTracker::default();
+        let client = test_client_with_events(&url, Arc::new(TrackedSessions(sessions.clone())));
+        client.update_config(WebSocketConfig {
+            auto_reconnect: false,
+            ..client.get_config()
+        });
+        let (connected, backend) = tokio::join!(client.connect(), accept(&listener));
+        connected.unwrap();
+        let mut backend = backend;
+        expect_hello(&mut backend).await;
+
+        // A session waiting for its end to be written
+        let session_id = Uuid::new_v4();
+        sessions.begin(session_id);
+        let (finished, outcome) = std::sync::mpsc::channel();
+        sessions.finish(session_id, Duration::from_secs(60), move |outcome| {
+            let _ = finished.send(outcome);
+        });
+        assert!(client.send_audio_data(vec![1; 8]).is_ok());
+        assert_eq!(
+            next_message(&mut backend).await,
+            ProtocolMessage::Audio(vec![1; 8])
+        );
+
+        drop(backend);
+        let outcome =
+            tokio::task::spawn_blocking(move || outcome.recv_timeout(Duration::from_secs(5)))
+                .await
+                .unwrap()
+                .expect("session was not abandoned");
+        assert!(outcome.timed_out);
+
+        // Dropped without a close handshake, so it ends in an error
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while is_connected(&client) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(matches!(client.get_status(), WebSocketStatus::Error(_)));
+        assert!(client.supervisor.lock().unwrap().is_none());
+        assert!(client.send_audio_data(vec![2; 8]).is_err());
+        assert!(!client
+            .connection
+            .audio_chunks
+            .push(ProtocolMessage::Audio(vec![3; 8])));
+        let retry = tokio::time::timeout(Duration::from_secs(2), listener.accept()).await;
+        assert!(retry.is_err());
+    }
+}
@@ -1,6 +1,49 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { useEffect, useState } from 'react';
 import './Dashboard.css';
 
+interface PlaybackPosition {
+  position_secs: number;
+  duration_secs: number;
+  playing: boolean;
+}
+
 export function Dashboard() {
+  const [playback, setPlayback] = useState<PlaybackPosition>({
+    position_secs: 0,
+    duration_secs: 0,
+    playing: false,
+  });
+  const [playbackError, setPlaybackError] = useState<string | null>(null);
+
+  useEffect(() => {
+    const unlistenPosition = listen<PlaybackPosition>('playback_position', (event) => {
+      setPlayback(event.payload);
+    });
+
+    return () => {
+      unlistenPosition.then((fn) => fn());
+    };
+  }, []);
+
+  const playLastRecording = async (startSecs = 0) => {
+    try {
+      setPlaybackError(null);
+      await invoke('play_last_recording', { startSecs });
+    } catch (error) {
+      setPlaybackError(String(error));
+    }
+  };
+
+  const stopPlayback = async () => {
+    try {
+      await invoke('stop_playback');
+    } catch (error) {
+      console.error('Failed to stop playback:', error);
+    }
+  };
+
   return (
     <div className="dashboard">
       <div className="dashboard-header">
@@ -14,6 +57,25 @@ export function Dashboard() {
           </p>
         </div>
       </div>
+      <div className="playback">
+        <button onClick={() => (playback.playing ? stopPlayback() : playLastRecording())}>
+          {playback.playing ? 'Stop' : 'Play Last Recording'}
+        </button>
+        <input
+          type="range"
+          className="playback-scrubber"
+          min="0"
+          max={playback.duration_secs}
+          step="0.1"
+          value={playback.position_secs}
+          disabled={playback.duration_secs === 0}
+          onChange={(e) => playLastRecording(parseFloat(e.target.value))}
+        />
+        <span className="playback-time">
+          {playback.position_secs.toFixed(1)}s / {playback.duration_secs.toFixed(1)}s
+        </span>
+      </div>
+      {playbackError && <p className="playback-error">{playbackError}</p>}
     </div>
   );
 }
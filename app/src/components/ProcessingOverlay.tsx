@@ -5,6 +5,7 @@ import './ProcessingOverlay.css';
 type ProcessingState =
   | 'idle'
   | 'recording'
+  | 'paused'
   | 'transcribing'
   | 'enhancing'
   | 'pasting'
@@ -12,11 +13,25 @@ type ProcessingState =
   | 'error';
 type ConnectionStatus = 'connected' | 'disconnected' | 'connecting';
 
+interface SessionOutcome {
+  session_id: string;
+  text: string | null;
+  timed_out: boolean;
+}
+
+interface AudioLevel {
+  rms_db: number;
+  peak_db: number;
+  is_clipping: boolean;
+  silence_detected: boolean;
+}
+
 export function ProcessingOverlay() {
   const [processingState, setProcessingState] = useState<ProcessingState>('idle');
   const [connectionStatus, setConnectionStatus] = useState<ConnectionStatus>('disconnected');
   const [errorMessage, setErrorMessage] = useState<string>('');
   const [audioLevel, setAudioLevel] = useState<number>(0);
+  const [isClipping, setIsClipping] = useState(false);
   const [isVisible, setIsVisible] = useState(false);
   const [autoHideTimeout, setAutoHideTimeout] = useState<ReturnType<typeof setTimeout> | null>(null);
 
@@ -52,14 +67,26 @@ export function ProcessingOverlay() {
       setProcessingState('transcribing');
     });
 
-    // Listen for audio chunks (for audio level visualization)
-    const unlistenAudioChunk = listen('audio_chunk', (event: any) => {
-      if (event.payload) {
-        // Calculate audio level from chunk data (simplified)
-        const audioData = event.payload as number[];
-        const level = Math.min(audioData.length / 1000, 1); // Simplified audio level
-        setAudioLevel(level);
-      }
+    const unlistenPause = listen('recording_paused', () => {
+      setProcessingState('paused');
+      setAudioLevel(0);
+    });
+
+    const unlistenResume = listen('recording_resumed', () => {
+      setProcessingState('recording');
+    });
+
+    const unlistenCancel = listen('recording_cancelled', () => {
+      setIsVisible(false);
+      setProcessingState('idle');
+    });
+
+    // Listen for input levels (for audio level visualization)
+    const unlistenAudioLevel = listen<AudioLevel>('audio_level', (event) => {
+      // Map -60..0 dBFS onto the width of the meter
+      const level = Math.min(Math.max((event.payload.rms_db + 60) / 60, 0), 1);
+      setAudioLevel(level);
+      setIsClipping(event.payload.is_clipping);
     });
 
     // Listen for transcription responses
@@ -69,6 +96,19 @@ export function ProcessingOverlay() {
       }
     });
 
+    // A session that ends without text has nothing to paste
+    const unlistenSessionFinished = listen<SessionOutcome>('session_finished', (event) => {
+      if (!event.payload.text) {
+        setProcessingState('error');
+        setErrorMessage(
+          event.payload.timed_out ? 'Transcription timed out' : 'No speech was recognized'
+        );
+        scheduleAutoHide(4000);
+      } else {
+        setProcessingState('pasting');
+      }
+    });
+
     // Listen for text pasting
     const unlistenTextPasted = listen('text_pasted', () => {
       setProcessingState('complete');
@@ -102,8 +142,12 @@ export function ProcessingOverlay() {
     return () => {
       unlistenStart.then((fn) => fn());
       unlistenStop.then((fn) => fn());
-      unlistenAudioChunk.then((fn) => fn());
+      unlistenPause.then((fn) => fn());
+      unlistenResume.then((fn) => fn());
+      unlistenCancel.then((fn) => fn());
+      unlistenAudioLevel.then((fn) => fn());
       unlistenTranscription.then((fn) => fn());
+      unlistenSessionFinished.then((fn) => fn());
       unlistenTextPasted.then((fn) => fn());
       unlistenWebSocketStatus.then((fn) => fn());
       unlistenRecordingError.then((fn) => fn());
@@ -124,6 +168,12 @@ export function ProcessingOverlay() {
           text: 'Listening...',
           className: 'recording-state',
         };
+      case 'paused':
+        return {
+          icon: <div className="paused-icon">⏸</div>,
+          text: 'Paused',
+          className: 'paused-state',
+        };
       case 'transcribing':
         return {
           icon: <div className="processing-spinner"></div>,
@@ -184,7 +234,10 @@ export function ProcessingOverlay() {
           {/* Audio level visualization when recording */}
           {processingState === 'recording' && (
             <div className="audio-level-container">
-              <div className="audio-level-bar" style={{ width: `${audioLevel * 100}%` }} />
+              <div
+                className={`audio-level-bar ${isClipping ? 'clipping' : ''}`}
+                style={{ width: `${audioLevel * 100}%` }}
+              />
             </div>
           )}
         </div>
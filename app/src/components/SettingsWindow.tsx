@@ -3,37 +3,180 @@ import { listen } from '@tauri-apps/api/event';
 import { useEffect, useState } from 'react';
 import './SettingsWindow.css';
 
+type ShortcutMode = 'toggle' | 'hold_to_talk';
+
+interface ShortcutBinding {
+  keys: string;
+  mode: ShortcutMode;
+}
+
 interface ShortcutConfig {
-  transcription: string;
-  edit: string;
+  transcription: ShortcutBinding;
+  edit: ShortcutBinding;
+  pause: ShortcutBinding;
+  hold_threshold_ms: number;
 }
 
+type ShortcutName = 'transcription' | 'edit' | 'pause';
+
 interface AudioDevice {
   name: string;
   is_default: boolean;
 }
 
+interface DspConfig {
+  high_pass_enabled: boolean;
+  noise_suppression_enabled: boolean;
+  agc_enabled: boolean;
+}
+
+interface LoopbackConfig {
+  enabled: boolean;
+  device: string | null;
+  mode: 'mix' | 'separate_channels';
+}
+
+interface ArchiveConfig {
+  enabled: boolean;
+  format: 'wav' | 'flac';
+  max_recordings: number;
+  max_age_days: number;
+  max_total_mb: number;
+}
+
+interface AudioConfig {
+  warm_mic: boolean;
+  pre_roll_ms: number;
+  language: string;
+  dsp: DspConfig;
+  loopback: LoopbackConfig;
+  archive: ArchiveConfig;
+}
+
+interface RecordingInfo {
+  session_id: string;
+  format: 'wav' | 'flac';
+  path: string;
+  size_bytes: number;
+  created_at: number;
+  duration_secs: number | null;
+}
+
+interface SoundCueConfig {
+  enabled: boolean;
+  output_device: string | null;
+  volume: number;
+}
+
+interface MicTestReport {
+  device_name: string;
+  sample_rate: number;
+  channels: number;
+  duration_secs: number;
+  noise_floor_db: number;
+  speech_level_db: number;
+  peak_db: number;
+  clipping_percent: number;
+  dc_offset: number;
+  dc_offset_ok: boolean;
+  verdict: 'Good' | 'Fair' | 'Poor';
+  suggestions: string[];
+}
+
+type OverflowPolicy = 'drop_oldest' | 'drop_newest';
+
+interface OutboundQueueConfig {
+  capacity: number;
+  overflow: OverflowPolicy;
+  spool_to_disk: boolean;
+  max_spool_mb: number;
+}
+
 interface WebSocketConfig {
   url: string;
   auto_reconnect: boolean;
   reconnect_interval: number;
+  queue: OutboundQueueConfig;
+}
+
+// Status as emitted on websocket_status: a plain string, { Error: message }
+// or { Reconnecting: { attempt, next_retry_at, last_error, queue } }
+function formatWebSocketStatus(status: any): string {
+  if (typeof status === 'string') {
+    return status;
+  }
+  if (status.Error) {
+    return `Error: ${status.Error}`;
+  }
+  if (status.Reconnecting) {
+    const { attempt, next_retry_at, queue } = status.Reconnecting;
+    const seconds = Math.max(0, Math.round((next_retry_at - Date.now()) / 1000));
+    const queued = `${queue.depth} queued`;
+    const dropped = queue.dropped > 0 ? `, ${queue.dropped} dropped (${queue.overflow})` : '';
+    return `Reconnecting (attempt ${attempt}, retrying in ${seconds}s, ${queued}${dropped})`;
+  }
+  return 'Connected';
 }
 
 export function SettingsWindow() {
   const [shortcuts, setShortcuts] = useState<ShortcutConfig>({
-    transcription: 'CommandOrControl+Ctrl+Space',
-    edit: 'CommandOrControl+Ctrl+E',
+    transcription: { keys: 'CommandOrControl+Ctrl+Space', mode: 'toggle' },
+    edit: { keys: 'CommandOrControl+Ctrl+E', mode: 'toggle' },
+    pause: { keys: 'CommandOrControl+Ctrl+P', mode: 'toggle' },
+    hold_threshold_ms: 300,
   });
   const [audioDevices, setAudioDevices] = useState<AudioDevice[]>([]);
   const [selectedDevice, setSelectedDevice] = useState<string>('');
   const [isRecording, setIsRecording] = useState(false);
+  const [audioConfig, setAudioConfig] = useState<AudioConfig>({
+    warm_mic: false,
+    pre_roll_ms: 500,
+    language: 'en-US',
+    dsp: {
+      high_pass_enabled: false,
+      noise_suppression_enabled: false,
+      agc_enabled: false,
+    },
+    loopback: {
+      enabled: false,
+      device: null,
+      mode: 'mix',
+    },
+    archive: {
+      enabled: false,
+      format: 'wav',
+      max_recordings: 50,
+      max_age_days: 30,
+      max_total_mb: 500,
+    },
+  });
+  const [recordings, setRecordings] = useState<RecordingInfo[]>([]);
+  const [warmMicOpen, setWarmMicOpen] = useState(false);
+  const [soundCues, setSoundCues] = useState<SoundCueConfig>({
+    enabled: false,
+    output_device: null,
+    volume: 0.3,
+  });
+  const [outputDevices, setOutputDevices] = useState<string[]>([]);
+  const [micTestRunning, setMicTestRunning] = useState(false);
+  const [micTestReport, setMicTestReport] = useState<MicTestReport | null>(null);
+  const [micTestError, setMicTestError] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
   const [websocketConfig, setWebsocketConfig] = useState<WebSocketConfig>({
     url: 'ws://localhost:8000/ws/transcribe',
     auto_reconnect: true,
     reconnect_interval: 5,
+    queue: {
+      capacity: 600,
+      overflow: 'drop_oldest',
+      spool_to_disk: false,
+      max_spool_mb: 100,
+    },
   });
   const [websocketStatus, setWebsocketStatus] = useState<string>('Disconnected');
+  // Connected, or retrying with audio kept in the queue
+  const websocketActive =
+    websocketStatus === 'Connected' || websocketStatus.startsWith('Reconnecting');
 
   useEffect(() => {
     loadSettings();
@@ -47,15 +190,18 @@ export function SettingsWindow() {
       setIsRecording(false);
     });
 
+    const unlistenWarmMic = listen<boolean>('warm_mic_changed', (event) => {
+      setWarmMicOpen(event.payload);
+    });
+
+    // Keep the microphone list current as devices are plugged in or removed
+    const unlistenDevices = listen<AudioDevice[]>('audio_devices_changed', (event) => {
+      setAudioDevices(event.payload);
+    });
+
     // Listen for WebSocket status changes
     const unlistenWebSocketStatus = listen('websocket_status', (event: any) => {
-      if (typeof event.payload === 'string') {
-        setWebsocketStatus(event.payload);
-      } else if (event.payload.Error) {
-        setWebsocketStatus(`Error: ${event.payload.Error}`);
-      } else {
-        setWebsocketStatus('Connected');
-      }
+      setWebsocketStatus(formatWebSocketStatus(event.payload));
     });
 
     // Listen for shortcut feedback
@@ -74,6 +220,8 @@ export function SettingsWindow() {
     return () => {
       unlistenStart.then((fn) => fn());
       unlistenStop.then((fn) => fn());
+      unlistenWarmMic.then((fn) => fn());
+      unlistenDevices.then((fn) => fn());
       unlistenWebSocketStatus.then((fn) => fn());
       unlistenShortcutUpdated.then((fn) => fn());
       unlistenShortcutError.then((fn) => fn());
@@ -92,6 +240,8 @@ export function SettingsWindow() {
       // Load audio devices
       const devices = await invoke<AudioDevice[]>('get_audio_devices');
       setAudioDevices(devices);
+      setOutputDevices(await invoke<string[]>('get_output_devices'));
+      setSoundCues(appSettings.sound_cues);
 
       // Set selected device from settings or default
       if (appSettings.selected_microphone) {
@@ -103,22 +253,30 @@ export function SettingsWindow() {
         }
       }
 
+      // Apply audio configuration
+      setAudioConfig({
+        warm_mic: appSettings.audio.warm_mic,
+        pre_roll_ms: appSettings.audio.pre_roll_ms,
+        language: appSettings.audio.language,
+        dsp: appSettings.audio.dsp,
+        loopback: appSettings.audio.loopback,
+        archive: appSettings.audio.archive,
+      });
+      if (appSettings.audio.archive.enabled) {
+        await loadRecordings();
+      }
+
       // Get recording status
       const recordingStatus = await invoke<boolean>('is_recording');
       setIsRecording(recordingStatus);
+      setWarmMicOpen(appSettings.audio.warm_mic && !recordingStatus);
 
       // Apply WebSocket configuration
       setWebsocketConfig(appSettings.websocket);
 
       // Get WebSocket status
       const wsStatus = await invoke<any>('get_websocket_status');
-      if (typeof wsStatus === 'string') {
-        setWebsocketStatus(wsStatus);
-      } else if (wsStatus.Error) {
-        setWebsocketStatus(`Error: ${wsStatus.Error}`);
-      } else {
-        setWebsocketStatus('Connected');
-      }
+      setWebsocketStatus(formatWebSocketStatus(wsStatus));
     } catch (error) {
       console.error('Failed to load settings:', error);
     } finally {
@@ -126,8 +284,16 @@ export function SettingsWindow() {
     }
   };
 
-  const handleShortcutChange = async (type: keyof ShortcutConfig, value: string) => {
-    setShortcuts((prev) => ({ ...prev, [type]: value }));
+  const handleShortcutModeChange = (type: ShortcutName, mode: ShortcutMode) => {
+    setShortcuts((prev) => ({ ...prev, [type]: { ...prev[type], mode } }));
+  };
+
+  const handleHoldThresholdChange = (value: number) => {
+    setShortcuts((prev) => ({ ...prev, hold_threshold_ms: value }));
+  };
+
+  const handleShortcutChange = async (type: ShortcutName, value: string) => {
+    setShortcuts((prev) => ({ ...prev, [type]: { ...prev[type], keys: value } }));
 
     // Validate shortcut format
     if (value.trim()) {
@@ -179,6 +345,60 @@ export function SettingsWindow() {
     }
   };
 
+  const saveAudioConfig = async (config: AudioConfig) => {
+    try {
+      setAudioConfig(config);
+
+      // Save to persistent settings, which also opens or closes the warm mic
+      const currentSettings = await invoke<any>('get_app_settings');
+      await invoke('save_app_settings', {
+        settings: {
+          ...currentSettings,
+          audio: {
+            ...currentSettings.audio,
+            ...config,
+            dsp: { ...currentSettings.audio.dsp, ...config.dsp },
+            loopback: { ...currentSettings.audio.loopback, ...config.loopback },
+            archive: { ...currentSettings.audio.archive, ...config.archive },
+          },
+        },
+      });
+
+      console.log('Audio settings saved');
+    } catch (error) {
+      console.error('Failed to save audio settings:', error);
+    }
+  };
+
+  const handleDspToggle = (key: keyof DspConfig, enabled: boolean) => {
+    saveAudioConfig({ ...audioConfig, dsp: { ...audioConfig.dsp, [key]: enabled } });
+  };
+
+  const handleLoopbackChange = (changes: Partial<LoopbackConfig>) => {
+    saveAudioConfig({ ...audioConfig, loopback: { ...audioConfig.loopback, ...changes } });
+  };
+
+  const handleArchiveChange = (changes: Partial<ArchiveConfig>) => {
+    saveAudioConfig({ ...audioConfig, archive: { ...audioConfig.archive, ...changes } });
+  };
+
+  const loadRecordings = async () => {
+    try {
+      setRecordings(await invoke<RecordingInfo[]>('list_recordings'));
+    } catch (error) {
+      console.error('Failed to list recordings:', error);
+    }
+  };
+
+  const deleteRecording = async (sessionId: string) => {
+    try {
+      await invoke('delete_recording', { sessionId });
+      await loadRecordings();
+    } catch (error) {
+      console.error('Failed to delete recording:', error);
+    }
+  };
+
   const toggleRecording = async () => {
     try {
       if (isRecording) {
@@ -191,6 +411,33 @@ export function SettingsWindow() {
     }
   };
 
+  const saveSoundCues = async (config: SoundCueConfig) => {
+    try {
+      setSoundCues(config);
+      const currentSettings = await invoke<any>('get_app_settings');
+      await invoke('save_app_settings', {
+        settings: { ...currentSettings, sound_cues: config },
+      });
+    } catch (error) {
+      console.error('Failed to save sound cue settings:', error);
+    }
+  };
+
+  const runMicrophoneTest = async () => {
+    setMicTestRunning(true);
+    setMicTestReport(null);
+    setMicTestError(null);
+    try {
+      const report = await invoke<MicTestReport>('run_microphone_test', { durationSecs: 3 });
+      setMicTestReport(report);
+    } catch (error) {
+      console.error('Microphone test failed:', error);
+      setMicTestError(String(error));
+    } finally {
+      setMicTestRunning(false);
+    }
+  };
+
   const handleWebSocketConfigChange = (
     key: keyof WebSocketConfig,
     value: string | boolean | number
@@ -198,6 +445,10 @@ export function SettingsWindow() {
     setWebsocketConfig((prev) => ({ ...prev, [key]: value }));
   };
 
+  const handleQueueConfigChange = (changes: Partial<OutboundQueueConfig>) => {
+    setWebsocketConfig((prev) => ({ ...prev, queue: { ...prev.queue, ...changes } }));
+  };
+
   const saveWebSocketConfig = async () => {
     try {
       await invoke('update_websocket_config', { config: websocketConfig });
@@ -266,21 +517,68 @@ export function SettingsWindow() {
             <input
               id="transcription-shortcut"
               type="text"
-              value={shortcuts.transcription}
+              value={shortcuts.transcription.keys}
               onChange={(e) => handleShortcutChange('transcription', e.target.value)}
               placeholder="CommandOrControl+Ctrl+Space"
             />
           </div>
+          <div className="setting-item">
+            <label htmlFor="transcription-mode">Transcription Mode:</label>
+            <select
+              id="transcription-mode"
+              value={shortcuts.transcription.mode}
+              onChange={(e) =>
+                handleShortcutModeChange('transcription', e.target.value as ShortcutMode)
+              }
+            >
+              <option value="toggle">Press to start, press again to stop</option>
+              <option value="hold_to_talk">Hold to talk</option>
+            </select>
+          </div>
+          {shortcuts.transcription.mode === 'hold_to_talk' && (
+            <div className="setting-item">
+              <label htmlFor="hold-threshold">Ignore Taps Shorter Than (ms):</label>
+              <input
+                id="hold-threshold"
+                type="number"
+                value={shortcuts.hold_threshold_ms}
+                onChange={(e) => handleHoldThresholdChange(parseInt(e.target.value))}
+                min="0"
+                max="2000"
+              />
+            </div>
+          )}
           <div className="setting-item">
             <label htmlFor="edit-shortcut">Edit Shortcut:</label>
             <input
               id="edit-shortcut"
               type="text"
-              value={shortcuts.edit}
+              value={shortcuts.edit.keys}
               onChange={(e) => handleShortcutChange('edit', e.target.value)}
               placeholder="CommandOrControl+Ctrl+E"
             />
           </div>
+          <div className="setting-item">
+            <label htmlFor="pause-shortcut">Pause/Resume Shortcut:</label>
+            <input
+              id="pause-shortcut"
+              type="text"
+              value={shortcuts.pause.keys}
+              onChange={(e) => handleShortcutChange('pause', e.target.value)}
+              placeholder="CommandOrControl+Ctrl+P"
+            />
+          </div>
+          <div className="setting-item">
+            <label htmlFor="pause-mode">Pause Mode:</label>
+            <select
+              id="pause-mode"
+              value={shortcuts.pause.mode}
+              onChange={(e) => handleShortcutModeChange('pause', e.target.value as ShortcutMode)}
+            >
+              <option value="toggle">Press to pause, press again to resume</option>
+              <option value="hold_to_talk">Paused while held</option>
+            </select>
+          </div>
           <button onClick={saveShortcuts} className="save-button">
             Save Shortcuts
           </button>
@@ -303,6 +601,292 @@ export function SettingsWindow() {
               ))}
             </select>
           </div>
+          <div className="setting-item">
+            <label htmlFor="language">Spoken Language:</label>
+            <select
+              id="language"
+              value={audioConfig.language}
+              onChange={(e) => saveAudioConfig({ ...audioConfig, language: e.target.value })}
+            >
+              <option value="en-US">English (US)</option>
+              <option value="en-GB">English (UK)</option>
+              <option value="de">German</option>
+              <option value="fr">French</option>
+              <option value="es">Spanish</option>
+              <option value="nl">Dutch</option>
+            </select>
+          </div>
+          <div className="setting-item">
+            <label>
+              <input
+                type="checkbox"
+                checked={audioConfig.warm_mic}
+                onChange={(e) => saveAudioConfig({ ...audioConfig, warm_mic: e.target.checked })}
+              />
+              Keep microphone warm (captures the moment before you press the shortcut)
+            </label>
+          </div>
+          {audioConfig.warm_mic && (
+            <div className="setting-item">
+              <label htmlFor="pre-roll">Pre-roll (ms):</label>
+              <input
+                id="pre-roll"
+                type="number"
+                value={audioConfig.pre_roll_ms}
+                onChange={(e) => setAudioConfig({ ...audioConfig, pre_roll_ms: parseInt(e.target.value) })}
+                onBlur={() => saveAudioConfig(audioConfig)}
+                min="0"
+                max="2000"
+              />
+              <span className={`status-indicator ${warmMicOpen ? 'recording' : 'idle'}`}>
+                {warmMicOpen ? 'Microphone open' : 'Microphone closed'}
+              </span>
+            </div>
+          )}
+          <div className="setting-item">
+            <label>Audio Cleanup:</label>
+            <label>
+              <input
+                type="checkbox"
+                checked={audioConfig.dsp.high_pass_enabled}
+                onChange={(e) => handleDspToggle('high_pass_enabled', e.target.checked)}
+              />
+              Remove low-frequency rumble
+            </label>
+            <label>
+              <input
+                type="checkbox"
+                checked={audioConfig.dsp.noise_suppression_enabled}
+                onChange={(e) => handleDspToggle('noise_suppression_enabled', e.target.checked)}
+              />
+              Suppress background noise
+            </label>
+            <label>
+              <input
+                type="checkbox"
+                checked={audioConfig.dsp.agc_enabled}
+                onChange={(e) => handleDspToggle('agc_enabled', e.target.checked)}
+              />
+              Automatic gain control
+            </label>
+          </div>
+          <div className="setting-item">
+            <label>
+              <input
+                type="checkbox"
+                checked={audioConfig.loopback.enabled}
+                onChange={(e) => handleLoopbackChange({ enabled: e.target.checked })}
+              />
+              Also record a second input (e.g. a monitor of your speakers for calls)
+            </label>
+          </div>
+          {audioConfig.loopback.enabled && (
+            <>
+              <div className="setting-item">
+                <label htmlFor="loopback-device">Second Input Device:</label>
+                <select
+                  id="loopback-device"
+                  value={audioConfig.loopback.device ?? ''}
+                  onChange={(e) => handleLoopbackChange({ device: e.target.value || null })}
+                >
+                  <option value="">Select a device</option>
+                  {audioDevices
+                    .filter((device) => device.name !== selectedDevice)
+                    .map((device) => (
+                      <option key={device.name} value={device.name}>
+                        {device.name}
+                      </option>
+                    ))}
+                </select>
+              </div>
+              <div className="setting-item">
+                <label htmlFor="loopback-mode">Combine Inputs:</label>
+                <select
+                  id="loopback-mode"
+                  value={audioConfig.loopback.mode}
+                  onChange={(e) =>
+                    handleLoopbackChange({ mode: e.target.value as LoopbackConfig['mode'] })
+                  }
+                >
+                  <option value="mix">Mix into one channel</option>
+                  <option value="separate_channels">Separate channels (microphone left)</option>
+                </select>
+              </div>
+            </>
+          )}
+          <div className="setting-item">
+            <label>
+              <input
+                type="checkbox"
+                checked={audioConfig.archive.enabled}
+                onChange={(e) => handleArchiveChange({ enabled: e.target.checked })}
+              />
+              Keep a copy of each recording on this computer
+            </label>
+          </div>
+          {audioConfig.archive.enabled && (
+            <>
+              <div className="setting-item">
+                <label htmlFor="archive-format">File Format:</label>
+                <select
+                  id="archive-format"
+                  value={audioConfig.archive.format}
+                  onChange={(e) =>
+                    handleArchiveChange({ format: e.target.value as ArchiveConfig['format'] })
+                  }
+                >
+                  <option value="wav">WAV</option>
+                  <option value="flac">FLAC (smaller)</option>
+                </select>
+              </div>
+              <div className="setting-item">
+                <label>Keep at most (0 for no limit):</label>
+                <label>
+                  <input
+                    type="number"
+                    value={audioConfig.archive.max_recordings}
+                    onChange={(e) =>
+                      handleArchiveChange({ max_recordings: parseInt(e.target.value) || 0 })
+                    }
+                    min="0"
+                  />
+                  recordings
+                </label>
+                <label>
+                  <input
+                    type="number"
+                    value={audioConfig.archive.max_age_days}
+                    onChange={(e) =>
+                      handleArchiveChange({ max_age_days: parseInt(e.target.value) || 0 })
+                    }
+                    min="0"
+                  />
+                  days
+                </label>
+                <label>
+                  <input
+                    type="number"
+                    value={audioConfig.archive.max_total_mb}
+                    onChange={(e) =>
+                      handleArchiveChange({ max_total_mb: parseInt(e.target.value) || 0 })
+                    }
+                    min="0"
+                  />
+                  MB in total
+                </label>
+              </div>
+              <div className="setting-item">
+                <label>Saved Recordings:</label>
+                <button onClick={loadRecordings}>Refresh</button>
+                {recordings.length === 0 ? (
+                  <p>No recordings saved yet.</p>
+                ) : (
+                  <ul className="recording-list">
+                    {recordings.map((recording) => (
+                      <li key={recording.session_id}>
+                        <span>
+                          {new Date(recording.created_at * 1000).toLocaleString()}
+                          {recording.duration_secs !== null &&
+                            ` · ${recording.duration_secs.toFixed(1)}s`}
+                          {` · ${recording.format.toUpperCase()}`}
+                          {` · ${(recording.size_bytes / 1024).toFixed(0)} KB`}
+                        </span>
+                        <button onClick={() => deleteRecording(recording.session_id)}>Delete</button>
+                      </li>
+                    ))}
+                  </ul>
+                )}
+              </div>
+            </>
+          )}
+          <div className="setting-item">
+            <label>
+              <input
+                type="checkbox"
+                checked={soundCues.enabled}
+                onChange={(e) => saveSoundCues({ ...soundCues, enabled: e.target.checked })}
+              />
+              Play sounds when recording starts, stops, fails or text is pasted
+            </label>
+          </div>
+          {soundCues.enabled && (
+            <>
+              <div className="setting-item">
+                <label htmlFor="cue-device">Sound Output Device:</label>
+                <select
+                  id="cue-device"
+                  value={soundCues.output_device ?? ''}
+                  onChange={(e) =>
+                    saveSoundCues({ ...soundCues, output_device: e.target.value || null })
+                  }
+                >
+                  <option value="">Default output</option>
+                  {outputDevices.map((device) => (
+                    <option key={device} value={device}>
+                      {device}
+                    </option>
+                  ))}
+                </select>
+              </div>
+              <div className="setting-item">
+                <label htmlFor="cue-volume">Sound Volume:</label>
+                <input
+                  id="cue-volume"
+                  type="range"
+                  min="0"
+                  max="1"
+                  step="0.05"
+                  value={soundCues.volume}
+                  onChange={(e) => setSoundCues({ ...soundCues, volume: parseFloat(e.target.value) })}
+                  onMouseUp={() => saveSoundCues(soundCues)}
+                  onKeyUp={() => saveSoundCues(soundCues)}
+                />
+              </div>
+            </>
+          )}
+          <div className="setting-item">
+            <label>Microphone Test:</label>
+            <div className="recording-controls">
+              <button
+                onClick={runMicrophoneTest}
+                className="toggle-button"
+                disabled={micTestRunning || isRecording}
+              >
+                {micTestRunning ? 'Listening… say a sentence' : 'Test Microphone'}
+              </button>
+              {micTestReport && (
+                <span className={`status-indicator mic-test-${micTestReport.verdict.toLowerCase()}`}>
+                  {micTestReport.verdict}
+                </span>
+              )}
+            </div>
+            {micTestError && <p className="mic-test-error">{micTestError}</p>}
+            {micTestReport && (
+              <div className="mic-test-report">
+                <p>
+                  {micTestReport.device_name}: {micTestReport.sample_rate} Hz,{' '}
+                  {micTestReport.channels} channel{micTestReport.channels === 1 ? '' : 's'}
+                </p>
+                <ul>
+                  <li>Noise floor: {micTestReport.noise_floor_db.toFixed(1)} dBFS</li>
+                  <li>Speech level: {micTestReport.speech_level_db.toFixed(1)} dBFS</li>
+                  <li>Peak: {micTestReport.peak_db.toFixed(1)} dBFS</li>
+                  <li>Clipping: {micTestReport.clipping_percent.toFixed(2)}%</li>
+                  <li>
+                    DC offset: {(micTestReport.dc_offset * 100).toFixed(2)}%{' '}
+                    {micTestReport.dc_offset_ok ? '(ok)' : '(too high)'}
+                  </li>
+                </ul>
+                {micTestReport.suggestions.length > 0 && (
+                  <ul className="mic-test-suggestions">
+                    {micTestReport.suggestions.map((suggestion) => (
+                      <li key={suggestion}>{suggestion}</li>
+                    ))}
+                  </ul>
+                )}
+              </div>
+            )}
+          </div>
           <div className="setting-item">
             <label>Recording Status:</label>
             <div className="recording-controls">
@@ -352,6 +936,49 @@ export function SettingsWindow() {
               max="60"
             />
           </div>
+          <div className="setting-item">
+            <label htmlFor="queue-capacity">Audio kept while offline (100ms chunks):</label>
+            <input
+              id="queue-capacity"
+              type="number"
+              value={websocketConfig.queue.capacity}
+              onChange={(e) => handleQueueConfigChange({ capacity: parseInt(e.target.value) || 1 })}
+              min="1"
+            />
+          </div>
+          <div className="setting-item">
+            <label htmlFor="queue-overflow">When the queue is full:</label>
+            <select
+              id="queue-overflow"
+              value={websocketConfig.queue.overflow}
+              onChange={(e) =>
+                handleQueueConfigChange({ overflow: e.target.value as OverflowPolicy })
+              }
+            >
+              <option value="drop_oldest">Drop the oldest audio</option>
+              <option value="drop_newest">Drop new audio</option>
+            </select>
+          </div>
+          <div className="setting-item">
+            <label>
+              <input
+                type="checkbox"
+                checked={websocketConfig.queue.spool_to_disk}
+                onChange={(e) => handleQueueConfigChange({ spool_to_disk: e.target.checked })}
+              />
+              Keep overflow on disk, up to
+            </label>
+            <input
+              type="number"
+              value={websocketConfig.queue.max_spool_mb}
+              onChange={(e) =>
+                handleQueueConfigChange({ max_spool_mb: parseInt(e.target.value) || 0 })
+              }
+              min="0"
+              disabled={!websocketConfig.queue.spool_to_disk}
+            />
+            MB
+          </div>
           <div className="setting-item">
             <label>Connection Status:</label>
             <div className="recording-controls">
@@ -366,10 +993,10 @@ export function SettingsWindow() {
                 Save Config
               </button>
               <button
-                onClick={websocketStatus === 'Connected' ? disconnectWebSocket : connectWebSocket}
+                onClick={websocketActive ? disconnectWebSocket : connectWebSocket}
                 className="toggle-button"
               >
-                {websocketStatus === 'Connected' ? 'Disconnect' : 'Connect'}
+                {websocketActive ? 'Disconnect' : 'Connect'}
               </button>
             </div>
           </div>
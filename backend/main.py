@@ -1,5 +1,8 @@
+import json
 import logging
 import os
+import struct
+import uuid
 from typing import Optional
 
 from deepgram import DeepgramClient, LiveOptions, LiveTranscriptionEvents
@@ -33,6 +36,34 @@ deepgram_client = DeepgramClient(DEEP_GRAM_KEY)
 logger.info("Initialized OpenAI and Deepgram clients")
 
 
+# Version of the message protocol, exchanged in "hello" messages. Text frames
+# are JSON objects tagged by "type"; audio is sent as binary frames.
+PROTOCOL_VERSION = 1
+
+# Audio frames from the app start with a header: magic, 16 byte session
+# UUID, then chunk sequence number and sample offset as little-endian u64
+CHUNK_MAGIC = b"GRL1"
+CHUNK_HEADER = struct.Struct("<4s16sQQ")
+
+
+# Text of sessions that have not ended yet, by session ID. Kept across
+# connections: after a reconnect the app starts the session again and
+# replays the rest of its audio, and the result should include all of it.
+open_sessions = {}
+MAX_OPEN_SESSIONS = 32
+
+
+def parse_audio_frame(data: bytes):
+    """Split a frame into (session_id, sequence, sample_offset, pcm).
+
+    Frames without a header are passed through as plain audio.
+    """
+    if len(data) < CHUNK_HEADER.size or not data.startswith(CHUNK_MAGIC):
+        return None, None, None, data
+    _, session, sequence, sample_offset = CHUNK_HEADER.unpack_from(data)
+    return uuid.UUID(bytes=session), sequence, sample_offset, data[CHUNK_HEADER.size :]
+
+
 # Pydantic Models
 class TranscriptionResult(BaseModel):
     text: str
@@ -119,58 +150,65 @@ async def transcribe_audio(websocket: WebSocket):
         await websocket.close()
         return
 
-    try:
-        # Configure Deepgram live transcription
-        dg_connection = deepgram_client.listen.websocket.v("1")
-
-        # Event handlers
-        async def on_message(self, result, **kwargs):
-            transcript = result.channel.alternatives[0].transcript
-            if transcript:
-                # Send transcription to client
-                await websocket.send_json(
-                    {
-                        "type": "transcription",
-                        "text": transcript,
-                        "is_final": result.is_final,
-                    }
-                )
+    # Final text of the current session, returned in "session_complete"
+    finals = []
+    enhanced = []
 
-                # If final transcription, optionally process with AI
-                if result.is_final and transcript.strip() and openai_client:
-                    try:
-                        # Process with OpenAI for enhancement
-                        response = openai_client.chat.completions.create(
-                            model=AZURE_DEPLOYMENT,
-                            messages=[
-                                {
-                                    "role": "system",
-                                    "content": "Improve the following transcribed text for clarity and fix any grammar issues. Keep the meaning intact.",
-                                },
-                                {"role": "user", "content": transcript},
-                            ],
-                            temperature=0.3,
-                            max_tokens=500,
-                        )
-
-                        enhanced_text = response.choices[0].message.content
-
-                        # Send enhanced version
-                        await websocket.send_json(
-                            {
-                                "type": "enhanced",
-                                "text": enhanced_text,
-                                "is_final": True,
-                            }
-                        )
-                    except Exception as e:
-                        logger.error(f"Error enhancing text: {str(e)}")
-
-        async def on_error(self, error, **kwargs):
-            logger.error(f"Deepgram error: {error}")
+    # Event handlers
+    async def on_message(self, result, **kwargs):
+        transcript = result.channel.alternatives[0].transcript
+        if transcript:
+            # Send transcription to client
             await websocket.send_json(
-                {"type": "error", "message": "Transcription error occurred"}
+                {
+                    "type": "transcription",
+                    "text": transcript,
+                    "is_final": result.is_final,
+                }
             )
+            if result.is_final:
+                finals.append(transcript)
+
+            # If final transcription, optionally process with AI
+            if result.is_final and transcript.strip() and openai_client:
+                try:
+                    # Process with OpenAI for enhancement
+                    response = openai_client.chat.completions.create(
+                        model=AZURE_DEPLOYMENT,
+                        messages=[
+                            {
+                                "role": "system",
+                                "content": "Improve the following transcribed text for clarity and fix any grammar issues. Keep the meaning intact.",
+                            },
+                            {"role": "user", "content": transcript},
+                        ],
+                        temperature=0.3,
+                        max_tokens=500,
+                    )
+
+                    enhanced_text = response.choices[0].message.content
+                    enhanced.append(enhanced_text)
+
+                    # Send enhanced version
+                    await websocket.send_json(
+                        {
+                            "type": "enhanced",
+                            "text": enhanced_text,
+                            "is_final": True,
+                        }
+                    )
+                except Exception as e:
+                    logger.error(f"Error enhancing text: {str(e)}")
+
+    async def on_error(self, error, **kwargs):
+        logger.error(f"Deepgram error: {error}")
+        await websocket.send_json(
+            {"type": "error", "message": "Transcription error occurred"}
+        )
+
+    async def open_deepgram(language="en-US", encoding=None, sample_rate=None, channels=None):
+        """Start a Deepgram live transcription for one session."""
+        dg_connection = deepgram_client.listen.websocket.v("1")
 
         # Register event handlers
         dg_connection.on(LiveTranscriptionEvents.Transcript, on_message)
@@ -179,7 +217,11 @@ async def transcribe_audio(websocket: WebSocket):
         # Configure transcription options
         options = LiveOptions(
             model="nova-2",
-            language="en-US",
+            language=language,
+            encoding=encoding,
+            sample_rate=sample_rate,
+            channels=channels,
+            multichannel=bool(channels and channels > 1),
             smart_format=True,
             punctuate=True,
             interim_results=True,
@@ -190,15 +232,108 @@ async def transcribe_audio(websocket: WebSocket):
         # Start the connection
         await dg_connection.start(options)
         logger.info("Deepgram connection started")
+        return dg_connection
 
-        # Handle incoming audio chunks
+    dg_connection = None
+    try:
+        # Handle incoming audio chunks and control messages
+        session_id = None
+        channels = 1
+        next_sequence = 0
+        next_offset = 0
         while True:
             try:
-                # Receive audio data from client
-                data = await websocket.receive_bytes()
+                message = await websocket.receive()
+                if message["type"] == "websocket.disconnect":
+                    logger.info("WebSocket disconnected")
+                    break
+
+                if message.get("text") is not None:
+                    control = json.loads(message["text"])
+                    kind = control.get("type")
+                    if kind == "hello":
+                        if control.get("version") != PROTOCOL_VERSION:
+                            logger.warning(
+                                f"Client speaks protocol version {control.get('version')}, expected {PROTOCOL_VERSION}"
+                            )
+                        await websocket.send_json(
+                            {"type": "hello", "version": PROTOCOL_VERSION}
+                        )
+                    elif kind == "session_start":
+                        if dg_connection:
+                            await dg_connection.finish()
+                        session_id = uuid.UUID(control["session_id"])
+                        channels = control.get("channels", 1)
+                        resumed = session_id in open_sessions
+                        # A resumed session continues where its audio left off
+                        next_sequence = None if resumed else 0
+                        next_offset = None if resumed else 0
+                        finals, enhanced = open_sessions.setdefault(session_id, ([], []))
+                        while len(open_sessions) > MAX_OPEN_SESSIONS:
+                            open_sessions.pop(next(iter(open_sessions)))
+                        logger.info(
+                            f"Session {session_id} {'resumed' if resumed else 'started'}: {control.get('encoding')} "
+                            f"{control.get('sample_rate')} Hz x{channels}, {control.get('language')}, "
+                            f"{control.get('mode')}"
+                        )
+                        dg_connection = await open_deepgram(
+                            language=control.get("language", "en-US"),
+                            encoding=control.get("encoding"),
+                            sample_rate=control.get("sample_rate"),
+                            channels=channels,
+                        )
+                    elif kind == "session_end":
+                        # Flush Deepgram so every final result is in
+                        if dg_connection:
+                            await dg_connection.finish()
+                            dg_connection = None
+                        finals, enhanced = open_sessions.pop(
+                            uuid.UUID(control["session_id"]), (finals, enhanced)
+                        )
+                        if control.get("cancelled"):
+                            logger.info(f"Session {control['session_id']} cancelled")
+                        else:
+                            await websocket.send_json(
+                                {
+                                    "type": "session_complete",
+                                    "session_id": control["session_id"],
+                                    "text": " ".join(finals),
+                                    "enhanced_text": " ".join(enhanced) or None,
+                                }
+                            )
+                            logger.info(f"Session {control['session_id']} complete")
+                        session_id = None
+                    else:
+                        logger.warning(f"Ignoring unknown message type {kind}")
+                    continue
+
+                data = message.get("bytes") or b""
+                chunk_session, sequence, sample_offset, audio = parse_audio_frame(
+                    data
+                )
+
+                if chunk_session is not None:
+                    if chunk_session != session_id:
+                        session_id = chunk_session
+                        logger.info(f"Audio session {session_id} started")
+                    else:
+                        if next_sequence is not None and sequence != next_sequence:
+                            logger.warning(
+                                f"Session {session_id}: expected chunk {next_sequence}, got {sequence}"
+                            )
+                        if next_offset is not None and sample_offset != next_offset:
+                            logger.warning(
+                                f"Session {session_id}: {sample_offset - next_offset} samples missing before chunk {sequence}"
+                            )
+                    next_sequence = sequence + 1
+                    next_offset = sample_offset + len(audio) // (2 * channels)
+
+                # Audio without a session_start, from older clients
+                if not dg_connection:
+                    dg_connection = await open_deepgram()
 
                 # Send to Deepgram
-                await dg_connection.send(data)
+                await dg_connection.send(audio)
 
             except WebSocketDisconnect:
                 logger.info("WebSocket disconnected")
@@ -209,7 +344,8 @@ async def transcribe_audio(websocket: WebSocket):
                 break
 
         # Clean up
-        await dg_connection.finish()
+        if dg_connection:
+            await dg_connection.finish()
 
     except Exception as e:
         logger.error(f"WebSocket error: {str(e)}")